@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io;
@@ -6,70 +8,191 @@ use std::io::Error;
 use std::io::Read;
 use std::io::Write;
 
+mod mysql;
+mod systemd;
+
 #[cfg(test)]
 mod test;
 
 
 pub struct IniFile {
-    lines: Vec<IniLine>,  // all lines
+    lines:         Vec<IniLine>,  // all lines
+    dialect:       IniDialect,    // rules used for parsing and creating lines
+    encoding:      IniEncoding,   // encoding used for reading and saving
+    invalid_lines: Vec<usize>,    // numbers of lines with invalid bytes
+    edit_options:  IniEditOptions,// how new and modified lines are written
 }
 
 impl IniFile {
-    fn new(lines: Vec<IniLine>) -> IniFile {
+    fn new(lines: Vec<IniLine>, dialect: IniDialect) -> IniFile {
         IniFile { 
-            lines: lines,
+            lines,
+            dialect,
+            encoding:      IniEncoding::Utf8,
+            invalid_lines: Vec::new(),
+            edit_options:  IniEditOptions::default(),
         }
     }
 }
 
 impl IniFile {
-    pub fn parse(file_name: Option<&str>) -> Result<IniFile, Error> {
+    pub fn parse(file_name: Option<&str>, dialect: &IniDialect, encoding: Option<IniEncoding>, strict: bool) -> Result<IniFile, IniError> { //encoding is detected if not given
+        let io_error = |error| IniError::io(file_name, error);
         let input = match file_name {
-            Some(file_name) => Box::new(File::open(file_name)?) as Box<Read>,
+            Some(file_name) => Box::new(File::open(file_name).map_err(io_error)?) as Box<Read>,
             None => Box::new(io::stdin()) as Box<Read>,
         };
         let mut reader = io::BufReader::new(input);
 
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None           => IniEncoding::detect(reader.fill_buf().map_err(io_error)?),
+        };
+        let mut reader = match encoding {
+            IniEncoding::Utf8 | IniEncoding::Utf8Bom => {
+                if reader.fill_buf().map_err(io_error)?.starts_with(UTF8_BOM) { reader.consume(UTF8_BOM.len()); } //byte order mark is not a part of the first line
+                Box::new(reader) as Box<dyn BufRead>
+            },
+            _ => { //decode whole file upfront
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes).map_err(io_error)?;
+                let text = encoding.decode(&bytes).map_err(|error| error.with_file_name(file_name))?;
+                Box::new(io::Cursor::new(text.into_bytes())) as Box<dyn BufRead>
+            },
+        };
+
         let mut lines = Vec::new();
-        let mut input_line = String::new();
-        while reader.read_line(&mut input_line).is_ok() {
-            if input_line.len() == 0 { break; }
+        let mut invalid_lines = Vec::new();
+        let mut input_bytes = Vec::new();
+        let mut line_number = 0;
+        let mut first_line_number = 0; //line number where continued line started
+        let mut continued_line = String::new(); //all lines joined by continuation so far
+        let mut continued_line_ending = ""; //line ending of the last joined line
+        while reader.read_until(b'\n', &mut input_bytes).map_err(io_error)? > 0 {
+            line_number += 1;
+            if continued_line.is_empty() { first_line_number = line_number; }
+
+            if let Err(error) = std::str::from_utf8(&input_bytes) {
+                if strict {
+                    let (_, column) = text_position(&from_bytes_lossless(&input_bytes[..error.valid_up_to()]));
+                    return Err(IniError::encoding(line_number, column, "invalid UTF-8 byte").with_file_name(file_name));
+                }
+                invalid_lines.push(line_number);
+            }
+            let mut input_line = from_bytes_lossless(&input_bytes);
 
-            if input_line.ends_with("\r\n") {
-                input_line.truncate(input_line.len() - 2);
-                let line = IniLine::new(&input_line, "\r\n");
-                lines.push(line);
+            let line_ending = if input_line.ends_with("\r\n") {
+                "\r\n"
             } else if input_line.ends_with("\n") {
-                input_line.truncate(input_line.len() - 1);
-                let line = IniLine::new(&input_line, "\n");
-                lines.push(line);
+                "\n"
             } else if input_line.ends_with("\r") { //doesn't happen really as read_line ends on \n only at this time
-                input_line.truncate(input_line.len() - 1);
-                let line = IniLine::new(&input_line, "\r");
-                lines.push(line);
+                "\r"
             } else { //last line might not have EOL character
-                let line = IniLine::new(&input_line, "");
+                ""
+            };
+            input_line.truncate(input_line.len() - line_ending.len());
+
+            let is_continuing = !continued_line.is_empty();
+            let is_comment = dialect.comment_length(input_line.trim_start()).is_some();
+            continued_line.push_str(&input_line);
+
+            let line = IniLine::parse(&continued_line, line_ending, dialect);
+            if dialect.continuations && !line_ending.is_empty() && (line.is_continued() || (is_continuing && is_comment)) { //comments within continuation are skipped
+                continued_line.push_str(line_ending);
+                continued_line_ending = line_ending;
+            } else {
+                if strict {
+                    if let Some((column, message)) = line.get_syntax_error(dialect, lines.is_empty()) {
+                        return Err(IniError::syntax(first_line_number, column, message).with_file_name(file_name));
+                    }
+                }
                 lines.push(line);
+                continued_line.truncate(0);
             }
 
-            input_line.truncate(0);
+            input_bytes.truncate(0);
+        }
+
+        if !continued_line.is_empty() { //file ended while line was still continued
+            continued_line.truncate(continued_line.len() - continued_line_ending.len());
+            if strict {
+                let (_, column) = text_position(&continued_line);
+                return Err(IniError::syntax(line_number, column - 1, "continued line is not finished").with_file_name(file_name));
+            }
+            lines.push(IniLine::parse(&continued_line, continued_line_ending, dialect));
         }
 
-        Ok(IniFile::new(lines))
+        let mut file = IniFile::new(lines, dialect.clone());
+        file.encoding = encoding;
+        file.invalid_lines = invalid_lines;
+        Ok(file)
     }
 
-    pub fn save(&self, file_name: Option<&str>) -> Result<(), Error> {
+    pub fn save(&self, file_name: Option<&str>) -> Result<(), IniError> {
+        let mut text = String::new();
+        for line in &self.lines {
+            text.push_str(&line.content.to_string());
+            text.push_str(&line.line_ending);
+        }
+        let bytes = self.encoding.encode(&text).map_err(|error| error.with_file_name(file_name))?; //encode before file gets truncated
+
+        let io_error = |error| IniError::io(file_name, error);
         let output = match file_name {
-            Some(file_name) => Box::new(File::create(file_name)?) as Box<Write>,
+            Some(file_name) => Box::new(File::create(file_name).map_err(io_error)?) as Box<Write>,
             None => Box::new(io::stdout()) as Box<Write>,
         };
         let mut writer = io::BufWriter::new(output);
+        writer.write_all(&bytes).map_err(io_error)?;
+        writer.flush().map_err(io_error)?;
+        Ok(())
+    }
+}
 
-        for line in &self.lines {
-            writer.write(line.content.to_string().as_bytes())?;
-            writer.write(line.line_ending.as_bytes())?;
+impl IniFile {
+    pub fn parse_effective(file_name: &str, dialect: &IniDialect, encoding: Option<IniEncoding>, strict: bool) -> Result<IniFile, IniError> { //file with its drop-ins or included files
+        if dialect.drop_ins {
+            IniFile::parse_with_drop_ins(file_name, dialect, encoding, strict)
+        } else if dialect.includes {
+            IniFile::parse_with_includes(file_name, dialect, encoding, strict, 0)
+        } else {
+            IniFile::parse(Some(file_name), dialect, encoding, strict)
         }
-        Ok(())
+    }
+
+    fn parse_with_drop_ins(file_name: &str, dialect: &IniDialect, encoding: Option<IniEncoding>, strict: bool) -> Result<IniFile, IniError> {
+        let mut file = IniFile::parse(Some(file_name), dialect, encoding, strict)?;
+        for drop_in_name in systemd::drop_in_files(file_name).map_err(|error| IniError::io(Some(file_name), error))? {
+            let mut drop_in = IniFile::parse(drop_in_name.to_str(), dialect, encoding, strict)?;
+            file.lines.append(&mut drop_in.lines);
+        }
+        Ok(file)
+    }
+
+    fn parse_with_includes(file_name: &str, dialect: &IniDialect, encoding: Option<IniEncoding>, strict: bool, depth: usize) -> Result<IniFile, IniError> {
+        let mut file = IniFile::parse(Some(file_name), dialect, encoding, strict)?;
+        let mut lines = Vec::new();
+        let mut section_line = None; //included file has its own sections
+        for line in file.lines.clone() {
+            lines.push(line.clone());
+            match &line.content {
+                IniContent::Section(_)       => { section_line = Some(line.clone()); },
+                IniContent::Directive(directive) if depth < mysql::MAX_INCLUDE_DEPTH => { //deeper includes are ignored
+                    let included_names = match directive.name.as_str() {
+                        "!include"    => vec![mysql::include_path(file_name, &directive.argument)],
+                        "!includedir" => mysql::include_dir_files(file_name, &directive.argument).map_err(|error| IniError::io(Some(file_name), error))?,
+                        _             => Vec::new(),
+                    };
+                    for included_name in included_names {
+                        let mut included = IniFile::parse_with_includes(&included_name.to_string_lossy(), dialect, encoding, strict, depth + 1)?;
+                        lines.append(&mut included.lines);
+                        if let Some(section_line) = &section_line { lines.push(section_line.clone()); } //continue where included file left off
+                    }
+                },
+                _ => { },
+            }
+        }
+        file.lines = lines;
+        Ok(file)
     }
 }
 
@@ -77,6 +200,18 @@ impl IniFile {
     pub fn line_count(&self) -> usize {
         self.lines.len()
     }
+
+    pub fn set_encoding(&mut self, encoding: IniEncoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn get_invalid_lines(&self) -> &Vec<usize> { //bytes are preserved but cannot be shown as text
+        &self.invalid_lines
+    }
+
+    pub fn set_edit_options(&mut self, edit_options: IniEditOptions) {
+        self.edit_options = edit_options;
+    }
 }
 
 impl IniFile {
@@ -97,6 +232,7 @@ impl IniFile {
                         had_entries = true;
                     },
                     IniContent::Comment(_) => { lines.push(line.reformatted(&line_ending.to_owned())); },
+                    IniContent::Directive(_) => { lines.push(line.reformatted(line_ending)); },
                     IniContent::Other(_)   => { },
                 }
             }
@@ -106,6 +242,37 @@ impl IniFile {
         }
     }
 
+    pub fn set_line_endings(&mut self, line_ending: &str) { //last line gets line ending too
+        for line in self.lines.iter_mut() {
+            if let IniContent::Entry(entry) = &mut line.content { //continued lines have line endings within value
+                entry.value = entry.value.replace("\r\n", "\n").replace('\n', line_ending);
+            }
+            line.line_ending = line_ending.to_string();
+        }
+    }
+
+    pub fn get_common_line_ending(&self) -> &str { //first one wins if there are multiple equally common
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for line in &self.lines {
+            let line_ending = line.get_line_ending();
+            if line_ending.is_empty() { continue; }
+            match counts.iter_mut().find(|(counted_line_ending, _)| *counted_line_ending == line_ending) {
+                Some((_, count)) => { *count += 1; },
+                None             => { counts.push((line_ending, 1)); },
+            }
+        }
+
+        let mut common_line_ending = "\n";
+        let mut common_count = 0;
+        for (line_ending, count) in counts {
+            if count > common_count {
+                common_line_ending = line_ending;
+                common_count = count;
+            }
+        }
+        common_line_ending
+    }
+
     pub fn trim(&mut self) {
         if self.lines.len() > 0 {
             let mut lines = Vec::new();
@@ -115,6 +282,7 @@ impl IniFile {
                     IniContent::Entry(_)   => { lines.push(line.trimmed()); },
                     IniContent::Other(_)   => { lines.push(line.trimmed()); },
                     IniContent::Comment(_) => { lines.push(line.trimmed()); },
+                    IniContent::Directive(_) => { lines.push(line.trimmed()); },
                 }
             }
 
@@ -131,6 +299,7 @@ impl IniFile {
                     IniContent::Section(_) => { lines.push(line); },
                     IniContent::Entry(_)   => { lines.push(line); },
                     IniContent::Other(_)   => { lines.push(line); },
+                    IniContent::Directive(_) => { lines.push(line); },
                     IniContent::Comment(_) => { },
                 }
             }
@@ -140,80 +309,245 @@ impl IniFile {
         }
     }
 
-    pub fn filter(&mut self, filter_section: Option<&str>, filter_key: Option<&str>) {
-        if self.lines.len() > 0 {
-            let mut is_section_matched = filter_section.is_none(); //match if no section
-            let mut lines = Vec::new();
-            for line in self.lines.clone() {
-                match line.clone().content {
-                    IniContent::Section(section) => {
-                        match filter_section {
-                            Some(filter_section) => {
-                                is_section_matched = section.name == filter_section;
-                                if is_section_matched && filter_key.is_none() { lines.push(line); }
-                            },
-                            None => { is_section_matched = true; },
+    pub fn sort_sections(&mut self) { //entries before the first section stay where they are
+        let section_indices: Vec<(usize, String)> = self.lines.iter().enumerate().filter_map(|(index, line)| {
+            match &line.content {
+                IniContent::Section(section) => Some((index, section.get_full_name())),
+                _                            => None,
+            }
+        }).collect();
+
+        let mut blocks = Vec::new();
+        for (order, (index, name)) in section_indices.iter().enumerate() {
+            let start_index = get_docs_index(&self.lines, *index);
+            let mut end_index = match section_indices.get(order + 1) {
+                Some((next_index, _)) => get_docs_index(&self.lines, *next_index),
+                None                  => self.lines.len(),
+            };
+            while end_index > index + 1 && is_empty_line(&self.lines[end_index - 1]) { end_index -= 1; } //empty lines separating sections stay in place
+            blocks.push((start_index, end_index, 0, name.clone()));
+        }
+
+        self.sort_blocks(blocks);
+    }
+
+    pub fn sort_keys(&mut self) { //keys are sorted only within their section
+        let mut blocks = Vec::new();
+        let mut section_order = 0;
+        for (index, line) in self.lines.iter().enumerate() {
+            match &line.content {
+                IniContent::Section(_)   => { section_order += 1; },
+                IniContent::Entry(entry) => { blocks.push((get_docs_index(&self.lines, index), index + 1, section_order, entry.get_key())); },
+                _                        => { },
+            }
+        }
+
+        self.sort_blocks(blocks);
+    }
+
+    fn sort_blocks(&mut self, blocks: Vec<(usize, usize, usize, String)>) { //start and end index, group, and name of each block
+        let mut sorted_blocks = blocks.clone();
+        sorted_blocks.sort_by(|(_, _, group, name), (_, _, other_group, other_name)| {
+            group.cmp(other_group).then_with(|| natural_cmp(name, other_name))
+        });
+
+        let mut lines = Vec::new();
+        let mut next_index = 0;
+        for ((start_index, end_index, _, _), (sorted_start_index, sorted_end_index, _, _)) in blocks.into_iter().zip(sorted_blocks) {
+            lines.extend_from_slice(&self.lines[next_index..start_index]); //lines between blocks stay in place
+            lines.extend_from_slice(&self.lines[sorted_start_index..sorted_end_index]);
+            next_index = end_index;
+        }
+        lines.extend_from_slice(&self.lines[next_index..]);
+
+        self.set_reordered_lines(lines);
+    }
+
+    fn set_reordered_lines(&mut self, mut lines: Vec<IniLine>) { //former last line might not be last anymore
+        let line_ending = self.get_common_line_ending().to_string();
+        let has_final_line_ending = !self.lines.last().is_some_and(|line| line.line_ending.is_empty());
+
+        for line in lines.iter_mut() {
+            if line.line_ending.is_empty() { line.line_ending = line_ending.clone(); }
+        }
+        if !has_final_line_ending {
+            if let Some(last_line) = lines.last_mut() { last_line.line_ending = String::new(); }
+        }
+
+        self.lines = lines;
+    }
+
+    pub fn dedupe(&mut self, policy: IniDedupe) -> Vec<IniIssue> { //repeated sections are merged into the first one and overridden keys are removed
+        let mut line_numbers = Vec::new(); //continued lines contain their line endings
+        let mut line_number = 1;
+        for line in &self.lines {
+            line_numbers.push(line_number);
+            line_number += 1 + line.content.to_string().matches('\n').count();
+        }
+
+        let mut issues = Vec::new();
+
+        let mut section_names: Vec<(usize, String)> = Vec::new(); //header index and name of each distinct section
+        let mut owners = vec![0; self.lines.len()]; //order of section each line goes to; lines before the first section stay first
+        let mut is_dropped = vec![false; self.lines.len()];
+        for (index, line) in self.lines.iter().enumerate() {
+            if let IniContent::Section(section) = &line.content {
+                let docs_index = get_docs_index(&self.lines, index);
+                let separator_index = docs_index - self.lines[..docs_index].iter().rev().take_while(|line| is_empty_line(line)).count(); //empty lines above header separate it from previous section
+
+                let full_name = section.get_full_name();
+                match section_names.iter().find(|(_, name)| self.is_section_match(section, name)) {
+                    Some((first_index, _)) => { //header is dropped together with its documentation and rest of section goes to the first one
+                        let first_line_number = line_numbers[*first_index];
+                        let column = section.prefix.chars().count() + 1;
+                        match policy {
+                            IniDedupe::Error             => { issues.push(IniIssue::error(line_numbers[index], column, &format!("section '{}' is already defined on line {}", full_name, first_line_number))); },
+                            _ if docs_index == index     => { issues.push(IniIssue::warning(line_numbers[index], column, &format!("section '{}' merged into line {}", full_name, first_line_number))); },
+                            _                            => { issues.push(IniIssue::warning(line_numbers[index], column, &format!("section '{}' merged into line {} and comments above it removed", full_name, first_line_number))); },
                         }
+                        is_dropped[separator_index..=index].fill(true);
                     },
-                    IniContent::Entry(entry)   => {
-                        if is_section_matched {
-                            match filter_key {
-                                Some(filter_key) => {
-                                    if entry.key == filter_key {
-                                        lines.push(line);
-                                    }
-                                },
-                                None => { lines.push(line); },
-                            }
+                    None => {
+                        let order = section_names.len() + 1;
+                        for (owner, is_section_matched) in owners.iter_mut().zip(self.get_section_matches(Some(&full_name))) {
+                            if is_section_matched { *owner = order; }
                         }
+                        owners[separator_index..index].fill(order); //documentation goes with its section
+                        section_names.push((index, full_name));
                     },
-                    IniContent::Other(_)   => { },
-                    IniContent::Comment(_) => { },
                 }
             }
+        }
+        let trailing_count = self.lines.iter().rev().take_while(|line| is_empty_line(line)).count(); //empty lines at the end of file stay there
+        owners[self.lines.len() - trailing_count..].fill(section_names.len() + 1);
+
+        let mut merged_indices: Vec<usize> = (0..self.lines.len()).filter(|index| !is_dropped[*index]).collect();
+        merged_indices.sort_by_key(|index| owners[*index]); //order within each section stays the same
+        let merged_lines: Vec<IniLine> = merged_indices.iter().map(|index| self.lines[*index].clone()).collect();
+
+        let mut key_positions: HashMap<(usize, String), Vec<usize>> = HashMap::new(); //positions of each key within its section
+        let mut section_order = 0;
+        for (position, line) in merged_lines.iter().enumerate() {
+            match &line.content {
+                IniContent::Section(_)   => { section_order += 1; },
+                IniContent::Entry(entry) => { key_positions.entry((section_order, self.dialect.compare.normalized(&entry.get_key()))).or_default().push(position); },
+                _                        => { },
+            }
+        }
 
-            self.lines.clear();
-            self.lines.append(&mut lines);
+        let mut is_removed = vec![false; merged_lines.len()];
+        for positions in key_positions.values().filter(|positions| positions.len() > 1) {
+            let kept_position = match policy {
+                IniDedupe::KeepLast => positions[positions.len() - 1],
+                _                   => positions[0],
+            };
+            for position in positions.iter().filter(|position| **position != kept_position) {
+                if let IniContent::Entry(entry) = &merged_lines[*position].content {
+                    let line_number = line_numbers[merged_indices[*position]];
+                    let kept_line_number = line_numbers[merged_indices[kept_position]];
+                    let column = entry.prefix.chars().count() + 1;
+                    match policy {
+                        IniDedupe::Error => { issues.push(IniIssue::error(line_number, column, &format!("key '{}' is already defined on line {}", entry.get_key(), kept_line_number))); },
+                        _                => { issues.push(IniIssue::warning(line_number, column, &format!("key '{}' removed in favor of line {}", entry.get_key(), kept_line_number))); },
+                    }
+                }
+                is_removed[get_docs_index(&merged_lines, *position)..=*position].fill(true); //documentation goes together with its entry
+            }
+        }
+        issues.sort_by_key(|issue| (issue.line_number, issue.column));
+
+        if policy != IniDedupe::Error {
+            let lines = merged_lines.into_iter().zip(is_removed).filter(|(_, is_removed)| !is_removed).map(|(line, _)| line).collect();
+            self.set_reordered_lines(lines);
         }
+
+        issues
     }
 
+    pub fn filter_localized(&mut self, filter_section: Option<&str>, filter_key: &str, locale: &str) {
+        let mut available_locales = Vec::new();
+        let section_matches = self.get_section_matches(filter_section);
+        for (index, line) in self.lines.iter().enumerate() {
+            if let IniContent::Entry(entry) = &line.content {
+                if let Some((key, entry_locale)) = entry.get_locale() {
+                    if section_matches[index] && self.is_key_match(&key, filter_key) { available_locales.push(entry_locale); }
+                }
+            }
+        }
 
-    pub fn delete(&mut self, filter_section: Option<&str>, filter_key: Option<&str>) {
-        assert!(filter_section.is_some() || filter_key.is_some());
+        match locale_fallbacks(locale).iter().find(|locale| available_locales.contains(locale)) {
+            Some(locale) => self.filter(filter_section, Some(&format!("{}[{}]", filter_key, locale))),
+            None         => self.filter(filter_section, Some(filter_key)), //unlocalized value is the last fallback
+        }
+    }
 
-        if self.lines.len() > 0 {
-            let mut is_section_matched = false;
-            let mut lines = Vec::new();
+    pub fn resolve_resets(&mut self) {
+        if self.dialect.empty_resets && self.lines.len() > 0 {
+            let compare = self.dialect.compare;
+            let mut section_name = String::new();
+            let mut lines: Vec<(String, IniLine)> = Vec::new(); //section name is kept alongside each line
             for line in self.lines.clone() {
                 match line.clone().content {
                     IniContent::Section(section) => {
-                        match filter_section {
-                            Some(filter_section) => {
-                                is_section_matched = section.name == filter_section;
-                                if !is_section_matched {
-                                    lines.push(line);
-                                } else if is_section_matched && filter_key.is_some() { //only write section if we're deleting the key
-                                    lines.push(line);
+                        section_name = compare.normalized_section(&section.get_full_name());
+                        lines.push((section_name.clone(), line));
+                    },
+                    IniContent::Entry(entry)     => {
+                        if entry.get_value_unquoted().is_empty() { //remove all previous values and reset itself
+                            lines.retain(|(line_section_name, line)| {
+                                match &line.content {
+                                    IniContent::Entry(previous_entry) => !(*line_section_name == section_name && compare.is_match(&previous_entry.get_key(), &entry.get_key())),
+                                    _                                 => true,
                                 }
-                            },
-                            None => {
-                                is_section_matched = false;
-                                lines.push(line);
-                            },
+                            });
+                        } else {
+                            lines.push((section_name.clone(), line));
                         }
                     },
+                    IniContent::Comment(_)       => { lines.push((section_name.clone(), line)); },
+                    IniContent::Directive(_)     => { lines.push((section_name.clone(), line)); },
+                    IniContent::Other(_)         => { lines.push((section_name.clone(), line)); },
+                }
+            }
+
+            self.lines.clear();
+            self.lines.extend(lines.into_iter().map(|(_, line)| line));
+        }
+    }
+
+    pub fn filter(&mut self, filter_section: Option<&str>, filter_key: Option<&str>) {
+        self.filter_lines(filter_section, filter_key, false);
+    }
+
+    pub fn filter_with_docs(&mut self, filter_section: Option<&str>, filter_key: Option<&str>) { //comments directly above are kept too
+        self.filter_lines(filter_section, filter_key, true);
+    }
+
+    fn filter_lines(&mut self, filter_section: Option<&str>, filter_key: Option<&str>, with_docs: bool) {
+        if self.lines.len() > 0 {
+            let section_matches = self.get_section_matches(filter_section); //match if no section or looking for entries before the first section
+            let mut lines = Vec::new();
+            for (index, line) in self.lines.iter().enumerate() {
+                let is_section_matched = section_matches[index];
+                let is_matched = match &line.content {
+                    IniContent::Section(_)   => is_section_matched && filter_section.is_some() && filter_key.is_none(),
                     IniContent::Entry(entry) => {
-                        if is_section_matched {
-                            match filter_key {
-                                Some(filter_key) => { if entry.key != filter_key { lines.push(line); } }, //append lines that are not filtered
-                                None => { },
-                            }
-                        } else {
-                            lines.push(line);
+                        match filter_key {
+                            Some(filter_key) => is_section_matched && self.is_key_match(&entry.get_key(), filter_key),
+                            None             => is_section_matched,
                         }
                     },
-                    IniContent::Comment(_) => { if !is_section_matched || filter_key.is_some() { lines.push(line); } },
-                    IniContent::Other(_)   => { if !is_section_matched || filter_key.is_some() { lines.push(line); } },
+                    IniContent::Other(_)     => false,
+                    IniContent::Comment(_)   => false,
+                    IniContent::Directive(_) => false,
+                };
+
+                if is_matched {
+                    if with_docs {
+                        let docs_index = get_docs_index(&self.lines, index);
+                        lines.extend_from_slice(&self.lines[docs_index..index]);
+                    }
+                    lines.push(line.clone());
                 }
             }
 
@@ -222,46 +556,187 @@ impl IniFile {
         }
     }
 
-    pub fn edit(&mut self, section_name: &str, key: &str, value: &str, modify_existing: bool, create_new: bool) {
-        let mut line_ending = "\n";
-        let mut had_section_matched = false;
-        let mut had_key_matched = false;
 
-        let cloned_lines = self.lines.clone();
-        if cloned_lines.len() > 0 { line_ending = &cloned_lines[0].get_line_ending(); }
+    pub fn delete(&mut self, filter_section: Option<&str>, filter_key: Option<&str>) {
+        assert!(filter_section.is_some() || filter_key.is_some());
 
         if self.lines.len() > 0 {
-            let mut is_section_matched = false;
-            for line in self.lines.clone() {
-                match line.content {
-                    IniContent::Section(section) => {
-                        is_section_matched = section.name == section_name;
-                        had_section_matched = had_section_matched || is_section_matched;
+            let section_matches = match filter_section {
+                Some(_) => self.get_section_matches(filter_section),
+                None    => vec![false; self.lines.len()],
+            };
+            let mut is_deleted = vec![false; self.lines.len()];
+            for (index, line) in self.lines.iter().enumerate() {
+                let is_section_matched = section_matches[index];
+                match &line.content {
+                    IniContent::Section(_)   => { is_deleted[index] = is_section_matched && filter_key.is_none(); }, //only keep section if we're deleting the key
+                    IniContent::Entry(entry) => {
+                        is_deleted[index] = is_section_matched && match filter_key {
+                            Some(filter_key) => self.is_key_match(&entry.get_key(), filter_key),
+                            None             => true,
+                        };
                     },
-                    IniContent::Entry(entry)     => {
-                        had_key_matched = had_key_matched  || (is_section_matched && (entry.key == key));
+                    IniContent::Comment(_)   => { is_deleted[index] = is_section_matched && filter_key.is_none(); },
+                    IniContent::Other(_)     => { is_deleted[index] = is_section_matched && filter_key.is_none(); },
+                    IniContent::Directive(_) => { }, //included files don't belong to section
+                }
+            }
+
+            for index in 0..self.lines.len() { //documentation goes wherever its entry or section goes
+                match self.lines[index].content {
+                    IniContent::Section(_) | IniContent::Entry(_) => {
+                        for docs_index in get_docs_index(&self.lines, index)..index {
+                            is_deleted[docs_index] = is_deleted[index];
+                        }
                     },
-                    IniContent::Comment(_)       => { },
-                    IniContent::Other(_)         => { },
+                    _ => { },
+                }
+            }
+
+            let mut lines: Vec<IniLine> = self.lines.drain(..).zip(is_deleted).filter(|(_, is_deleted)| !is_deleted).map(|(line, _)| line).collect();
+            self.lines.append(&mut lines);
+        }
+    }
+
+    pub fn contains(&self, section_name: Option<&str>, key: Option<&str>) -> bool {
+        let mut file = IniFile::new(self.lines.clone(), self.dialect.clone());
+        file.filter(section_name, key);
+        !file.lines.is_empty()
+    }
+
+    pub fn add_flag(&mut self, section_name: &str, key: &str) { //existing key is left as is
+        if !self.contains(Some(section_name), Some(key)) {
+            let entry = self.create_flag(key);
+            self.append_entry(section_name, entry);
+        }
+    }
+
+    pub fn copy_entry(&mut self, section_name: &str, key: &str, new_section_name: &str) { //existing key in new section is replaced
+        if self.is_same_section(section_name, new_section_name) { return; }
+
+        let line_ending = self.get_common_line_ending().to_string();
+        let mut new_lines = Vec::new();
+        let section_matches = self.get_section_matches(Some(section_name));
+        for (index, line) in self.lines.iter().enumerate() {
+            match &line.content {
+                IniContent::Entry(entry) if section_matches[index] && self.is_key_match(&entry.get_key(), key) => {
+                    for docs_line in &self.lines[get_docs_index(&self.lines, index)..=index] { //entry goes together with its documentation
+                        let mut new_line = docs_line.clone();
+                        if new_line.line_ending.is_empty() { new_line.line_ending = line_ending.clone(); } //last line might be without line ending
+                        new_lines.push(new_line);
+                    }
+                },
+                _ => { },
+            }
+        }
+
+        if !new_lines.is_empty() {
+            self.delete(Some(new_section_name), Some(key));
+            self.append_lines(new_section_name, new_lines);
+        }
+    }
+
+    pub fn move_entry(&mut self, section_name: &str, key: &str, new_section_name: &str) {
+        if self.is_same_section(section_name, new_section_name) { return; }
+
+        self.copy_entry(section_name, key, new_section_name);
+        self.delete(Some(section_name), Some(key));
+    }
+
+    pub fn overlay(&mut self, other: &IniFile) { //keys from other file override existing ones; new ones are added with their documentation
+        let edit_options = self.edit_options.clone();
+        self.edit_options = IniEditOptions { raw: true, ..IniEditOptions::default() }; //values in other file are already encoded
+
+        let line_ending = self.get_common_line_ending().to_string();
+        let mut section_name = String::new(); //entries before the first section
+        for (index, line) in other.lines.iter().enumerate() {
+            match &line.content {
+                IniContent::Section(section) => {
+                    section_name = section.get_full_name();
+                    if !self.contains(Some(&section_name), None) { self.append_lines(&section_name, Vec::new()); }
+                },
+                IniContent::Entry(entry) => {
+                    let key = entry.get_key();
+                    if self.contains(Some(&section_name), Some(&key)) {
+                        if !entry.separator.is_empty() { self.edit(&section_name, &key, entry.get_value(), true, false); } //existing key is enough for flag
+                    } else {
+                        let mut new_lines = Vec::new();
+                        for docs_line in &other.lines[get_docs_index(&other.lines, index)..=index] {
+                            let mut new_line = docs_line.clone();
+                            new_line.line_ending = line_ending.clone(); //formatting of the first file wins
+                            new_lines.push(new_line);
+                        }
+                        self.append_lines(&section_name, new_lines);
+                    }
+                },
+                _ => { },
+            }
+        }
+
+        self.edit_options = edit_options;
+    }
+
+    pub fn edit(&mut self, section_name: &str, key: &str, value: &str, modify_existing: bool, create_new: bool) {
+        let mut had_key_matched = false;
+        let mut last_key_index = None; //where duplicates are allowed only the last (effective) entry is changed
+
+        let section_matches = self.get_section_matches(Some(section_name));
+        for (index, line) in self.lines.iter().enumerate() {
+            if let IniContent::Entry(entry) = &line.content {
+                if section_matches[index] && self.is_key_match(&entry.get_key(), key) {
+                    had_key_matched = true;
+                    last_key_index = Some(index);
                 }
             }
         }
 
         if modify_existing && had_key_matched {
             let mut lines = Vec::new();
-            let mut is_section_matched = false;
+            let is_last_only = self.dialect.get_duplicates() == IniDuplicates::Allowed;
 
-            for line in self.lines.clone() {
+            for (index, line) in self.lines.clone().into_iter().enumerate() {
                 match line.clone().content {
-                    IniContent::Section(section) => {
-                        is_section_matched = section.name == section_name;
-                        lines.push(line);
-                    },
+                    IniContent::Section(_)       => { lines.push(line); },
                     IniContent::Entry(entry)     => {
-                        let is_key_matched = is_section_matched && (entry.key == key);
-                        if is_key_matched {
+                        let is_key_matched = section_matches[index] && self.is_key_match(&entry.get_key(), key) && (!is_last_only || last_key_index == Some(index));
+                        let is_reset = self.dialect.empty_resets && !entry.separator.is_empty() && entry.get_value().is_empty();
+                        if is_key_matched && is_reset { //reset stays and new value goes after it
+                            lines.push(line);
+                            if let Some(new_comment) = self.create_comment() {
+                                push_line(&mut lines, IniLine {
+                                    content:     IniContent::Comment(new_comment),
+                                    line_ending: self.get_common_line_ending().to_string(),
+                                });
+                            }
+                            let value = self.encoded_value(section_name, value, None);
+                            push_line(&mut lines, IniLine {
+                                content:     IniContent::Entry(self.create_entry(key, &value)),
+                                line_ending: self.get_common_line_ending().to_string(),
+                            });
+                        } else if is_key_matched {
+                            if let Some(new_comment) = self.create_comment() { //comment written earlier is updated while other documentation is kept
+                                let docs_index = get_docs_index(&lines, lines.len());
+                                let old_comment = lines[docs_index..].iter_mut().rev().find_map(|docs_line| {
+                                    match &mut docs_line.content {
+                                        IniContent::Comment(comment) if comment.text.trim().starts_with(COMMENT_MARKER) => Some(comment),
+                                        _                                                                              => None,
+                                    }
+                                });
+                                match old_comment {
+                                    Some(comment) => {
+                                        if comment.text.trim() != new_comment.text { *comment = IniComment { prefix: comment.prefix.clone(), ..new_comment }; } //same comment is left as is
+                                    },
+                                    None => {
+                                        push_line(&mut lines, IniLine {
+                                            content:     IniContent::Comment(new_comment),
+                                            line_ending: self.get_common_line_ending().to_string(),
+                                        });
+                                    },
+                                }
+                            }
+                            let value = self.encoded_value(section_name, value, Some(&entry));
                             lines.push(IniLine { 
-                                content:     IniContent::Entry(entry.with_modified_value(value)),
+                                content:     IniContent::Entry(entry.with_modified_value(&value)),
                                 line_ending: line.get_line_ending().to_string(),
                             });
                         } else {
@@ -269,6 +744,7 @@ impl IniFile {
                         }
                     },
                     IniContent::Comment(_)       => { lines.push(line); },
+                    IniContent::Directive(_)     => { lines.push(line); },
                     IniContent::Other(_)         => { lines.push(line); },
                 }
             }
@@ -276,131 +752,601 @@ impl IniFile {
             self.lines.clear();
             self.lines.append(&mut lines);
         } else if create_new {
-            if !had_section_matched { //if section doesn't exist, we don't need to search for one
-                self.lines.push(IniLine { 
-                    content:     IniContent::Section(IniSection::create(section_name)),
-                    line_ending: line_ending.to_string(),
-                });
-                self.lines.push(IniLine { 
-                    content:     IniContent::Entry(IniEntry::create(key, value)),
-                    line_ending: line_ending.to_string(),
-                });
-            } else { //we need to append to existing section
-                let mut lines = Vec::new();
-                let mut is_section_matched = false;
-                let mut was_section_matched = false;
-                let mut consecutive_other_count = 0; //to keep track how far back we need to go to insert item
-                let mut done = false;
-
-                for line in self.lines.clone() {
-                    match line.clone().content {
-                        IniContent::Section(section) => {
-                            is_section_matched = section.name == section_name;
-                        },
-                        IniContent::Entry(_)         => { consecutive_other_count = 0; },
-                        IniContent::Comment(_)       => { consecutive_other_count = 0; },
-                        IniContent::Other(_)         => { consecutive_other_count += 1; },
-                    }
+            let value = self.encoded_value(section_name, value, None);
+            let entry = self.create_entry(key, &value);
+            self.append_entry(section_name, entry);
+        }
+    }
+
+    fn append_entry(&mut self, section_name: &str, entry: IniEntry) { //entry goes at the end of section
+        let line_ending = self.get_common_line_ending().to_string();
+
+        let mut new_lines = Vec::new(); //entry with its comment
+        if let Some(new_comment) = self.create_comment() {
+            new_lines.push(IniLine {
+                content:     IniContent::Comment(new_comment),
+                line_ending: line_ending.to_string(),
+            });
+        }
+        new_lines.push(IniLine {
+            content:     IniContent::Entry(entry),
+            line_ending: line_ending.to_string(),
+        });
+
+        self.append_lines(section_name, new_lines);
+    }
+
+    fn append_lines(&mut self, section_name: &str, new_lines: Vec<IniLine>) { //lines go at the end of section unless position is given
+        let line_ending = self.get_common_line_ending().to_string();
+        let mut had_section_matched = section_name.is_empty(); //there is always space before the first section
+
+        for line in &self.lines {
+            if let IniContent::Section(section) = &line.content {
+                had_section_matched = had_section_matched || self.is_section_match(section, section_name);
+            }
+        }
 
-                    if !done && !is_section_matched && was_section_matched {
-                        let new_index = lines.len() - consecutive_other_count;
-                        lines.insert(new_index, IniLine {
-                            content:     IniContent::Entry(IniEntry::create(key, value)),
-                            line_ending: line_ending.to_string(),
-                        });
-                        done = true;
-                        consecutive_other_count = 0;
+        if !had_section_matched { //if section doesn't exist, we don't need to search for one
+            let mut section_lines = vec![IniLine { 
+                content:     IniContent::Section(self.create_section(section_name)),
+                line_ending: line_ending.to_string(),
+            }];
+            section_lines.extend(new_lines);
+            let empty_line = IniLine {
+                content:     IniContent::Other(IniOther::empty()),
+                line_ending: line_ending.to_string(),
+            };
+            let has_empty_lines = self.has_empty_lines_between_sections();
+
+            match self.get_section_position_index(section_name) {
+                Some(new_index) => { //goes before another section
+                    if has_empty_lines { section_lines.push(empty_line); }
+                    for (offset, section_line) in section_lines.into_iter().enumerate() {
+                        insert_line(&mut self.lines, new_index + offset, section_line);
+                    }
+                },
+                None => {
+                    if has_empty_lines && self.lines.last().is_some_and(|last_line| !is_empty_line(last_line)) { push_line(&mut self.lines, empty_line); }
+                    for section_line in section_lines {
+                        push_line(&mut self.lines, section_line);
                     }
+                },
+            }
+        } else if let Some(new_index) = self.get_position_index(section_name) { //position within existing section
+            for (offset, new_line) in new_lines.into_iter().enumerate() {
+                insert_line(&mut self.lines, new_index + offset, new_line);
+            }
+        } else { //we need to append to existing section
+            let mut lines = Vec::new();
+            let section_matches = self.get_section_matches(Some(section_name));
+            let mut was_section_matched = section_name.is_empty();
+            let mut consecutive_other_count = 0; //to keep track how far back we need to go to insert item
+            let mut done = false;
 
-                    lines.push(line);
-                    was_section_matched = is_section_matched;
+            for (index, line) in self.lines.clone().into_iter().enumerate() {
+                let is_section_matched = section_matches[index];
+                match line.content {
+                    IniContent::Section(_)       => { },
+                    IniContent::Entry(_)         => { consecutive_other_count = 0; },
+                    IniContent::Comment(_)       => { consecutive_other_count = 0; },
+                    IniContent::Directive(_)     => { consecutive_other_count = 0; },
+                    IniContent::Other(_)         => { consecutive_other_count += 1; },
                 }
 
-                if !done {
+                if !done && !is_section_matched && was_section_matched {
                     let new_index = lines.len() - consecutive_other_count;
-                    lines.insert(new_index, IniLine {
-                        content:     IniContent::Entry(IniEntry::create(key, value)),
-                        line_ending: line_ending.to_string(),
-                    });
+                    for (offset, new_line) in new_lines.iter().enumerate() {
+                        insert_line(&mut lines, new_index + offset, new_line.clone());
+                    }
+                    done = true;
+                    consecutive_other_count = 0;
                 }
 
-                self.lines.clear();
-                self.lines.append(&mut lines);
+                if let IniContent::Section(_) = line.content { consecutive_other_count = 0; } //lines before section header don't belong to section
+                lines.push(line);
+                was_section_matched = is_section_matched;
+            }
+
+            if !done {
+                let new_index = lines.len() - consecutive_other_count;
+                for (offset, new_line) in new_lines.into_iter().enumerate() {
+                    insert_line(&mut lines, new_index + offset, new_line);
+                }
             }
+
+            self.lines.clear();
+            self.lines.append(&mut lines);
         }
     }
 }
 
-impl IntoIterator for IniFile {
-    type Item = IniLine;
-    type IntoIter = IniFileIntoIterator;
-
-    fn into_iter(self) -> Self::IntoIter {
-        IniFileIntoIterator {
-            lines: self.lines,
-            index: 0,
-        }
+fn insert_line(lines: &mut Vec<IniLine>, index: usize, mut line: IniLine) { //all inserts should go through here
+    if index > 0 && lines[index - 1].line_ending.is_empty() { //last line without line ending stays last line without it
+        lines[index - 1].line_ending = line.line_ending.clone();
+        line.line_ending = String::new();
     }
+    lines.insert(index, line);
 }
 
-pub struct IniFileIntoIterator {
-    lines: Vec<IniLine>,
-    index: usize,
+fn push_line(lines: &mut Vec<IniLine>, line: IniLine) {
+    let index = lines.len();
+    insert_line(lines, index, line);
 }
 
-impl Iterator for IniFileIntoIterator {
-    type Item = IniLine;
-    fn next(&mut self) -> Option<IniLine> {
-        if self.index < self.lines.len() {
-            let line = self.lines[self.index].clone();
-            self.index += 1;
-            Some(line)
-        } else {
-            None
+fn natural_cmp(text: &str, other_text: &str) -> Ordering { //numbers are compared by value and letters without regard to case
+    let mut chars = text.chars().peekable();
+    let mut other_chars = other_text.chars().peekable();
+    loop {
+        match (chars.peek().copied(), other_chars.peek().copied()) {
+            (None, None)    => { return text.cmp(other_text); }, //exact comparison only if otherwise equal
+            (None, Some(_)) => { return Ordering::Less; },
+            (Some(_), None) => { return Ordering::Greater; },
+            (Some(c), Some(other_c)) if c.is_ascii_digit() && other_c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) { number.push(c); }
+                let mut other_number = String::new();
+                while let Some(c) = other_chars.next_if(|c| c.is_ascii_digit()) { other_number.push(c); }
+
+                let number = number.trim_start_matches('0');
+                let other_number = other_number.trim_start_matches('0');
+                let ordering = number.len().cmp(&other_number.len()).then_with(|| number.cmp(other_number));
+                if ordering != Ordering::Equal { return ordering; }
+            },
+            (Some(c), Some(other_c)) => {
+                let ordering = c.to_lowercase().cmp(other_c.to_lowercase());
+                if ordering != Ordering::Equal { return ordering; }
+                chars.next();
+                other_chars.next();
+            },
         }
     }
 }
 
-
-#[derive(Clone)]
-pub struct IniLine {
-    content:     IniContent,  // line content
-    line_ending: String,      // line ending
+fn is_empty_line(line: &IniLine) -> bool {
+    match &line.content {
+        IniContent::Other(other) => other.text.trim().is_empty(),
+        _                        => false,
+    }
+}
+
+fn get_docs_index(lines: &[IniLine], index: usize) -> usize { //comments directly above entry or section document it
+    let mut docs_index = index;
+    while docs_index > 0 {
+        match lines[docs_index - 1].content {
+            IniContent::Comment(_) => { docs_index -= 1; },
+            _                      => { break; },
+        }
+    }
+    docs_index
+}
+
+impl IniFile {
+    fn is_section_match(&self, section: &IniSection, section_name: &str) -> bool {
+        let compare = self.dialect.compare;
+        match section.get_subsection() {
+            Some((name, subsection)) => { //subsection is always case-sensitive
+                let is_full_name_match = section_name.split_once('.').is_some_and(|(other_name, other_subsection)| {
+                    (compare.normalized_section(&name) == compare.normalized_section(other_name)) && (subsection == other_subsection)
+                });
+                (section.name == section_name) || is_full_name_match
+            },
+            None => compare.normalized_section(&section.name) == compare.normalized_section(section_name),
+        }
+    }
+
+    fn get_section_matches(&self, section_name: Option<&str>) -> Vec<bool> { //whether each line belongs to matching section; all lines match if there is no section
+        let mut is_section_matched = section_name.unwrap_or("").is_empty(); //empty section covers entries before the first section
+        self.lines.iter().map(|line| {
+            if let IniContent::Section(section) = &line.content {
+                is_section_matched = match section_name {
+                    Some(section_name) => self.is_section_match(section, section_name),
+                    None               => true,
+                };
+            }
+            is_section_matched
+        }).collect()
+    }
+
+    fn is_same_section(&self, section_name: &str, other_section_name: &str) -> bool {
+        let compare = self.dialect.compare;
+        compare.normalized_section(section_name) == compare.normalized_section(other_section_name)
+    }
+
+    fn is_key_match(&self, key: &str, other_key: &str) -> bool {
+        self.dialect.compare.is_match(key, other_key)
+    }
+
+    fn create_section(&self, section_name: &str) -> IniSection {
+        let uses_subsections = self.lines.iter().any(|line| {
+            match &line.content {
+                IniContent::Section(section) => section.get_subsection().is_some(),
+                _                            => false,
+            }
+        });
+
+        match section_name.find('.') {
+            Some(dot_index) if uses_subsections && dot_index > 0 => { //follow existing [name "subsection"] style
+                IniSection::create_subsection(&section_name[..dot_index], &section_name[dot_index + 1..])
+            },
+            _ => IniSection::create(section_name),
+        }
+    }
+
+    fn create_entry(&self, key: &str, value: &str) -> IniEntry { //value must be already encoded
+        let mut entry = IniEntry::create(key, value);
+        entry.escapes = self.dialect.escapes;
+        if self.dialect.quoted_keys && key != "@" { entry.key = IniEntry::quoted(key); } //@ is default value
+        entry
+    }
+
+    fn create_flag(&self, key: &str) -> IniEntry { //key without separator and value
+        let mut entry = self.create_entry(key, "");
+        entry.separator = String::new();
+        entry
+    }
+
+    fn get_position_index(&self, section_name: &str) -> Option<usize> { //none if lines should go at the end of section
+        let section_matches = self.get_section_matches(Some(section_name));
+        let mut position_index = None;
+        for (index, line) in self.lines.iter().enumerate() {
+            match &line.content {
+                IniContent::Entry(entry) if section_matches[index] => {
+                    match &self.edit_options.position {
+                        IniPosition::First                                                   => { return Some(get_docs_index(&self.lines, index)); },
+                        IniPosition::Before(key) if self.is_key_match(&entry.get_key(), key) => { return Some(get_docs_index(&self.lines, index)); }, //documentation stays with its entry
+                        IniPosition::After(key)  if self.is_key_match(&entry.get_key(), key) => { position_index = Some(index + 1); }, //after the last one
+                        _                                                                    => { },
+                    }
+                },
+                _ => { },
+            }
+        }
+        position_index
+    }
+
+    fn get_section_position_index(&self, section_name: &str) -> Option<usize> { //none if section should go at the end of file
+        let mut was_section_matched = false;
+        for (index, line) in self.lines.iter().enumerate() {
+            if let IniContent::Section(section) = &line.content {
+                let is_position = match &self.edit_options.section_position {
+                    IniSectionPosition::Last               => false,
+                    IniSectionPosition::Before(other_name) => self.is_section_match(section, other_name),
+                    IniSectionPosition::After(other_name)  => was_section_matched && !self.is_section_match(section, other_name),
+                    IniSectionPosition::Sorted             => natural_cmp(&section.get_full_name(), section_name) == Ordering::Greater,
+                };
+                if is_position { return Some(get_docs_index(&self.lines, index)); } //documentation stays with its section
+
+                if let IniSectionPosition::After(other_name) = &self.edit_options.section_position {
+                    was_section_matched = was_section_matched || self.is_section_match(section, other_name);
+                }
+            }
+        }
+        None
+    }
+
+    fn has_empty_lines_between_sections(&self) -> bool { //true only if every section is separated that way
+        let mut separated_count = 0;
+        for (index, line) in self.lines.iter().enumerate() {
+            if let IniContent::Section(_) = &line.content {
+                let docs_index = get_docs_index(&self.lines, index);
+                if docs_index > 0 {
+                    if !is_empty_line(&self.lines[docs_index - 1]) { return false; }
+                    separated_count += 1;
+                }
+            }
+        }
+        separated_count > 0
+    }
+
+    fn create_comment(&self) -> Option<IniComment> { //comment to go above edited entry
+        let text = self.edit_options.comment.as_deref()?;
+        let prelude = self.dialect.comment_chars.first().map(|comment_char| comment_char.as_str()).unwrap_or(";");
+        Some(IniComment::from_text(prelude, &format!("{} {}", COMMENT_MARKER, text.trim())))
+    }
+
+    fn encoded_value(&self, section_name: &str, value: &str, entry: Option<&IniEntry>) -> String { //existing entry keeps its quotes
+        if self.edit_options.raw { return value.to_string(); }
+
+        match entry.and_then(|entry| entry.get_quote_char()) {
+            Some(quote_char) => self.dialect.encoded_value(value, quote_char, true),
+            None             => {
+                let section_matches = self.get_section_matches(Some(section_name));
+                let quote_char = self.lines.iter().zip(section_matches).find_map(|(line, is_section_matched)| { //new quotes follow the ones already used in the same section
+                    match &line.content {
+                        IniContent::Entry(entry) if is_section_matched => entry.get_quote_char(),
+                        _                                              => None,
+                    }
+                }).unwrap_or('"');
+                self.dialect.encoded_value(value, quote_char, false)
+            },
+        }
+    }
+}
+
+impl IniFile {
+    pub fn edit_list(&mut self, section_name: &str, key: &str, item: &str, add_item: bool) {
+        let separator = match self.dialect.list_separator {
+            Some(separator) => separator,
+            None            => return,
+        };
+        let escaped_item = item.replace('\\', "\\\\").replace(separator, &format!("\\{}", separator));
+
+        let mut had_key_matched = false;
+        let section_matches = self.get_section_matches(Some(section_name));
+        let mut lines = Vec::new();
+        for (index, line) in self.lines.clone().into_iter().enumerate() {
+            match line.clone().content {
+                IniContent::Entry(entry) if section_matches[index] && self.is_key_match(&entry.get_key(), key) => {
+                    had_key_matched = true;
+                    let mut items = entry.get_list_items(separator);
+                    let has_item = items.contains(&escaped_item);
+                    if add_item && !has_item {
+                        items.push(escaped_item.clone());
+                    } else if !add_item && has_item {
+                        items.retain(|existing_item| *existing_item != escaped_item);
+                    } else {
+                        lines.push(line);
+                        continue;
+                    }
+
+                    let has_last_separator = entry.get_value().trim().ends_with(separator) || (entry.get_list_items(separator).len() <= 1); //lists should end with separator unless file says otherwise
+                    let mut value = items.join(&separator.to_string());
+                    if has_last_separator && !items.is_empty() { value.push(separator); }
+                    lines.push(IniLine {
+                        content:     IniContent::Entry(entry.with_modified_value(&value)),
+                        line_ending: line.get_line_ending().to_string(),
+                    });
+                },
+                _ => { lines.push(line); },
+            }
+        }
+
+        self.lines.clear();
+        self.lines.append(&mut lines);
+
+        if add_item && !had_key_matched { //item is already escaped
+            let entry = self.create_entry(key, &format!("{}{}", escaped_item, separator));
+            self.append_entry(section_name, entry);
+        }
+    }
+}
+
+impl IniFile {
+    pub fn lint(&self) -> Vec<IniIssue> {
+        let mut issues = Vec::new();
+        let first_line_ending = self.lines.iter().map(|line| line.get_line_ending()).find(|line_ending| !line_ending.is_empty()).unwrap_or("");
+
+        let mut section_line_numbers = HashMap::new();
+        let mut key_line_numbers = HashMap::new();
+        let mut section_name = None; //no section before the first one
+        let mut line_number = 1;
+        for (index, line) in self.lines.iter().enumerate() {
+            match &line.content {
+                IniContent::Section(section) => {
+                    let full_name = section.get_full_name();
+                    let column = section.prefix.chars().count() + 1;
+                    match section_line_numbers.get(&self.dialect.compare.normalized_section(&full_name)) {
+                        Some(first_line_number) if self.dialect.duplicates != IniDuplicates::Allowed => {
+                            issues.push(IniIssue::warning(line_number, column, &format!("section '{}' is already defined on line {}", full_name, first_line_number)));
+                        },
+                        Some(_) => { },
+                        None    => { section_line_numbers.insert(self.dialect.compare.normalized_section(&full_name), line_number); },
+                    }
+                    section_name = Some(self.dialect.compare.normalized_section(&full_name));
+                },
+                IniContent::Entry(entry) => {
+                    let key = entry.get_key();
+                    let column = entry.prefix.chars().count() + 1;
+                    if self.dialect.sections && section_name.is_none() {
+                        issues.push(IniIssue::warning(line_number, column, &format!("key '{}' is outside of any section", key)));
+                    }
+                    let section_key = (section_name.clone().unwrap_or_default(), self.dialect.compare.normalized(&key));
+                    match key_line_numbers.get(&section_key) {
+                        Some(first_line_number) if self.dialect.duplicates != IniDuplicates::Allowed => {
+                            issues.push(IniIssue::warning(line_number, column, &format!("key '{}' is already defined on line {}", key, first_line_number)));
+                        },
+                        Some(_) => { },
+                        None    => { key_line_numbers.insert(section_key, line_number); },
+                    }
+                },
+                IniContent::Comment(_) => { },
+                IniContent::Directive(_) | IniContent::Other(_) => {
+                    if let Some((column, message)) = line.get_syntax_error(&self.dialect, index == 0) {
+                        issues.push(IniIssue::error(line_number, column, message));
+                    }
+                },
+            }
+
+            if !line.get_line_ending().is_empty() && (line.get_line_ending() != first_line_ending) {
+                let message = format!("line ending {} differs from {} used by the first line", line_ending_name(line.get_line_ending()), line_ending_name(first_line_ending));
+                issues.push(IniIssue::warning(line_number, 1, &message));
+            }
+
+            line_number += 1 + line.content.to_string().matches('\n').count(); //continued lines contain their line endings
+        }
+
+        issues
+    }
+}
+
+fn line_ending_name(line_ending: &str) -> &str {
+    match line_ending {
+        "\r\n" => "CRLF",
+        "\n"   => "LF",
+        "\r"   => "CR",
+        _      => "none",
+    }
+}
+
+impl IntoIterator for IniFile {
+    type Item = IniLine;
+    type IntoIter = IniFileIntoIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IniFileIntoIterator {
+            lines: self.lines,
+            index: 0,
+        }
+    }
+}
+
+pub struct IniFileIntoIterator {
+    lines: Vec<IniLine>,
+    index: usize,
+}
+
+impl Iterator for IniFileIntoIterator {
+    type Item = IniLine;
+    fn next(&mut self) -> Option<IniLine> {
+        if self.index < self.lines.len() {
+            let line = self.lines[self.index].clone();
+            self.index += 1;
+            Some(line)
+        } else {
+            None
+        }
+    }
+}
+
+
+fn locale_fallbacks(locale: &str) -> Vec<String> { //lang_COUNTRY.ENCODING@MODIFIER is tried without encoding, then without country, and then without modifier
+    let (locale, modifier) = match locale.find('@') {
+        Some(at_index) => (&locale[..at_index], Some(&locale[at_index + 1..])),
+        None           => (locale, None),
+    };
+    let locale = match locale.find('.') {
+        Some(dot_index) => &locale[..dot_index],
+        None            => locale,
+    };
+    let (language, country) = match locale.find('_') {
+        Some(underscore_index) => (&locale[..underscore_index], Some(&locale[underscore_index + 1..])),
+        None                   => (locale, None),
+    };
+
+    let mut locales = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) { locales.push(format!("{}_{}@{}", language, country, modifier)); }
+    if let Some(country) = country { locales.push(format!("{}_{}", language, country)); }
+    if let Some(modifier) = modifier { locales.push(format!("{}@{}", language, modifier)); }
+    locales.push(language.to_string());
+    locales
+}
+
+
+const LOSSLESS_FIRST: u32 = 0x10FF00; //last 256 characters of private use area stand for raw bytes
+
+pub fn from_bytes_lossless(bytes: &[u8]) -> String { //invalid UTF-8 bytes are kept as characters they can be restored from
+    fn push_valid(text: &mut String, valid: &str) {
+        for c in valid.chars() {
+            if (c as u32) >= LOSSLESS_FIRST { //escape valid characters that would be mistaken for bytes
+                let mut buffer = [0; 4];
+                for b in c.encode_utf8(&mut buffer).bytes() { text.push(lossless_char(b)); }
+            } else {
+                text.push(c);
+            }
+        }
+    }
+
+    let mut text = String::new();
+    let mut remaining = bytes;
+    loop {
+        match std::str::from_utf8(remaining) {
+            Ok(valid) => {
+                push_valid(&mut text, valid);
+                return text;
+            },
+            Err(err) => {
+                let (valid, invalid) = remaining.split_at(err.valid_up_to());
+                push_valid(&mut text, std::str::from_utf8(valid).unwrap());
+                let invalid_length = err.error_len().unwrap_or(invalid.len());
+                for b in &invalid[..invalid_length] { text.push(lossless_char(*b)); }
+                remaining = &invalid[invalid_length..];
+            },
+        }
+    }
+}
+
+pub fn to_bytes_lossless(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for c in text.chars() {
+        if (c as u32) >= LOSSLESS_FIRST {
+            bytes.push(((c as u32) - LOSSLESS_FIRST) as u8);
+        } else {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+        }
+    }
+    bytes
+}
+
+fn lossless_char(b: u8) -> char {
+    std::char::from_u32(LOSSLESS_FIRST + b as u32).unwrap()
+}
+
+fn text_position(text: &str) -> (usize, usize) { //line number and column of the character following text
+    let line_number = text.matches('\n').count() + 1;
+    let column = text.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line_number, column)
+}
+
+
+#[derive(Clone)]
+pub struct IniLine {
+    content:     IniContent,  // line content
+    line_ending: String,      // line ending
 }
 
 impl IniLine {
-    fn new(raw_content: &str, line_ending: &str) -> IniLine {
+    fn parse(raw_content: &str, line_ending: &str, dialect: &IniDialect) -> IniLine {
+        if dialect.includes {
+            if let Some(directive) = IniDirective::parse(raw_content) {
+                return IniLine {
+                    content: IniContent::Directive(directive),
+                    line_ending: line_ending.to_string(),
+                };
+            }
+        }
+
         let mut prefix = Vec::new();
         let mut suffix = Vec::new();
         let mut section_name = Vec::new();
+        let mut prelude = Vec::new();
         let mut key = Vec::new();
         let mut separator = Vec::new();
         let mut value = Vec::new();
         let mut comment_prelude = Vec::new();
+        let mut comment_prelude_length = 0; //comment can start with multiple characters (e.g. //)
         let mut comment_text = Vec::new();
 
-        enum State { Prefix, Suffix, SectionName, Key, Separator, Value, CommentText }
+        enum State { Prefix, Suffix, SectionName, Prelude, Key, Separator, Value, CommentText, InlineComment }
         let mut state = State::Prefix;
 
         enum Kind { Section, Entry, Comment, Other }
         let mut kind = Kind::Other;
 
-        for c in raw_content.chars() {
+        let mut value_quote = '\0'; //quote character value is currently enclosed in (only tracked for inline comments)
+        let mut value_escape = false;
+        let mut key_quoted = false; //key is currently within quotes
+        let mut key_escape = false;
+
+        for (index, c) in raw_content.char_indices() {
+            let is_comment_start = || dialect.comment_length(&raw_content[index..]).is_some();
             match &state {
                 State::Prefix => {
                     if c.is_whitespace() {
                         prefix.push(c);
-                    } else if c == '[' {
+                    } else if c == '[' && dialect.sections {
                         kind = Kind::Section;
                         state = State::SectionName;
-                    } else if c == ';' || c == '#' {
+                    } else if let Some(comment_length) = dialect.comment_length(&raw_content[index..]) {
                         comment_prelude.push(c);
+                        comment_prelude_length = comment_length;
                         kind = Kind::Comment;
                         state = State::CommentText;
                     } else if c == '=' { //this is invalid, just move to suffix
                         suffix.push(c);
                         state = State::Suffix;
                     } else {
+                        if c == '"' && dialect.quoted_keys { key_quoted = true; }
                         key.push(c);
                         kind = Kind::Entry;
                         state = State::Key;
@@ -410,6 +1356,10 @@ impl IniLine {
                 State::Suffix => {
                     if !c.is_whitespace() {
                         match kind {
+                            Kind::Entry if dialect.inline_comments && value_quote == '\0' && is_comment_start() => {
+                                suffix.push(c);
+                                state = State::InlineComment;
+                            },
                             Kind::Entry => {
                                 value.append(&mut suffix);
                                 value.push(c);
@@ -430,8 +1380,36 @@ impl IniLine {
                     }
                 },
 
+                State::Prelude => {
+                    if c.is_whitespace() {
+                        prelude.push(c);
+                    } else if c == '=' { //export is the key itself
+                        let keyword_length = prelude.iter().take_while(|c| !c.is_whitespace()).count();
+                        separator.extend(prelude.drain(keyword_length..));
+                        key.append(&mut prelude);
+                        separator.push(c);
+                        state = State::Separator;
+                    } else {
+                        key.push(c);
+                        state = State::Key;
+                    }
+                },
+
                 State::Key => {
-                    if c.is_whitespace() || c == '=' {
+                    if key_quoted {
+                        if key_escape {
+                            key_escape = false;
+                        } else if c == '\\' {
+                            key_escape = true;
+                        } else if c == '"' {
+                            key_quoted = false;
+                        }
+                        key.push(c);
+                    } else if c.is_whitespace() && dialect.export_prefix && prelude.is_empty() && key.iter().collect::<String>() == "export" {
+                        prelude.append(&mut key);
+                        prelude.push(c);
+                        state = State::Prelude;
+                    } else if c.is_whitespace() || dialect.separators.contains(&c) {
                         separator.push(c);
                         state = State::Separator;
                     } else {
@@ -440,19 +1418,33 @@ impl IniLine {
                 },
 
                 State::Separator => {
+                    let has_separator = separator.iter().any(|c| dialect.separators.contains(c));
                     if c.is_whitespace() {
                         separator.push(c);
-                    } else if c == '=' {
-                        if separator.contains(&'=') { //second equals belongs to value
+                    } else if dialect.separators.contains(&c) {
+                        if has_separator { //second separator belongs to value
                             value.push(c);
                             state = State::Value;
                         } else {
                             separator.push(c);
                         }
-                    } else if separator.contains(&'=') { //any non-whitespace after separator is value
+                    } else if has_separator && dialect.inline_comments && is_comment_start() && separator.last().is_some_and(|c| c.is_whitespace()) {
+                        suffix.push(c); //comment right after separator means value is empty
+                        state = State::InlineComment;
+                    } else if !has_separator && dialect.flags && dialect.inline_comments && is_comment_start() {
+                        suffix.append(&mut separator); //flag followed by comment
+                        suffix.push(c);
+                        state = State::InlineComment;
+                    } else if has_separator { //any non-whitespace after separator is value
+                        if dialect.inline_comments && (c == '"' || c == '\'') { value_quote = c; }
                         value.push(c);
                         state = State::Value;
+                    } else if dialect.key_spaces { //whitespace was within key
+                        key.append(&mut separator);
+                        key.push(c);
+                        state = State::Key;
                     } else {
+                        suffix.append(&mut prelude);
                         suffix.append(&mut key);
                         suffix.append(&mut separator);
                         suffix.push(c);
@@ -462,7 +1454,16 @@ impl IniLine {
                 },
 
                 State::Value => {
-                    if c.is_whitespace() {
+                    if value_quote != '\0' {
+                        if value_escape {
+                            value_escape = false;
+                        } else if c == '\\' && value_quote == '"' {
+                            value_escape = true;
+                        } else if c == value_quote {
+                            value_quote = '\0';
+                        }
+                        value.push(c);
+                    } else if c.is_whitespace() {
                         suffix.push(c);
                         state = State::Suffix;
                     } else {
@@ -471,23 +1472,43 @@ impl IniLine {
                 },
 
                 State::CommentText => {
-                    comment_text.push(c);
+                    if comment_prelude.len() < comment_prelude_length {
+                        comment_prelude.push(c);
+                    } else {
+                        comment_text.push(c);
+                    }
+                },
+
+                State::InlineComment => {
+                    suffix.push(c);
                 },
             }
         }
 
         //fixups
+        let has_separator = separator.iter().any(|c| dialect.separators.contains(c));
+        let is_flag = dialect.flags && !key.iter().any(|c| c.is_whitespace()); //flag is a single word
         match state {
             State::SectionName => { //never finished SectionName
                 prefix.push('[');
                 prefix.append(&mut section_name);
                 kind = Kind::Other;
             },
+            State::Prelude => {
+                prefix.append(&mut prelude);
+                kind = Kind::Other;
+            },
+            State::Key if is_flag => { }, //key alone is a flag
+            State::Separator if !has_separator && is_flag => {
+                suffix.append(&mut separator);
+            },
             State::Key => {
+                prefix.append(&mut prelude);
                 prefix.append(&mut key);
                 kind = Kind::Other;
             },
-            State::Separator if !separator.contains(&'=') => {
+            State::Separator if !has_separator => {
+                prefix.append(&mut prelude);
                 prefix.append(&mut key);
                 prefix.append(&mut separator);
                 kind = Kind::Other;
@@ -504,7 +1525,7 @@ impl IniLine {
                 }
             },
             Kind::Entry => {
-                let entry = IniEntry::new(&prefix, &key, &separator, &value, &suffix);
+                let entry = IniEntry::new(&prefix, &prelude, &key, &separator, &value, &suffix, dialect.escapes);
                 IniLine {
                     content: IniContent::Entry(entry),
                     line_ending: line_ending.to_string(),
@@ -542,6 +1563,7 @@ impl IniLine {
             IniContent::Section(section) => IniLine { content: IniContent::Section(section.reformatted()), line_ending: line_ending.to_string() },
             IniContent::Entry(entry)     => IniLine { content: IniContent::Entry(entry.reformatted()),     line_ending: line_ending.to_string() },
             IniContent::Comment(comment) => IniLine { content: IniContent::Comment(comment.reformatted()), line_ending: line_ending.to_string() },
+            IniContent::Directive(directive) => IniLine { content: IniContent::Directive(directive.reformatted()), line_ending: line_ending.to_string() },
             IniContent::Other(_)         => IniLine { content: IniContent::Other(IniOther::empty()),       line_ending: line_ending.to_string() },
         }
     }
@@ -551,16 +1573,52 @@ impl IniLine {
             IniContent::Section(section) => IniLine { content: IniContent::Section(section.trimmed()), line_ending: self.line_ending.clone() },
             IniContent::Entry(entry)     => IniLine { content: IniContent::Entry(entry.trimmed()),     line_ending: self.line_ending.clone() },
             IniContent::Comment(comment) => IniLine { content: IniContent::Comment(comment.trimmed()), line_ending: self.line_ending.clone() },
+            IniContent::Directive(directive) => IniLine { content: IniContent::Directive(directive.trimmed()), line_ending: self.line_ending.clone() },
             IniContent::Other(other)     => IniLine { content: IniContent::Other(other.trimmed()),     line_ending: self.line_ending.clone() },
         }
     }
 }
 
 impl IniLine {
+    fn is_continued(&self) -> bool {
+        match &self.content {
+            IniContent::Entry(entry) if entry.escapes == IniEscapes::Git => { //escaped backslash doesn't continue line
+                let backslash_count = entry.value.chars().rev().take_while(|c| *c == '\\').count();
+                backslash_count % 2 == 1 && entry.suffix.is_empty()
+            },
+            IniContent::Entry(entry) => entry.value.ends_with('\\') && entry.suffix.is_empty(),
+            _                        => false,
+        }
+    }
+
     fn get_line_ending(&self) -> &str {
         self.line_ending.as_str()
     }
 
+    fn get_syntax_error(&self, dialect: &IniDialect, is_first_line: bool) -> Option<(usize, &'static str)> { //column and description
+        match &self.content {
+            IniContent::Other(other) => {
+                let text = other.text.trim();
+                if text.is_empty() || (is_first_line && dialect.headers.contains(&text)) { return None; }
+
+                let column = other.text.chars().take_while(|c| c.is_whitespace()).count() + 1;
+                if dialect.sections && text.starts_with('[') {
+                    Some((column, "section is not closed"))
+                } else {
+                    Some((column, "line is not a section, an entry, or a comment"))
+                }
+            },
+            IniContent::Directive(directive) => {
+                if (directive.name == "!include") || (directive.name == "!includedir") {
+                    None
+                } else {
+                    Some((directive.prefix.chars().count() + 1, "unknown directive"))
+                }
+            },
+            _ => None,
+        }
+    }
+
     pub fn get_content(&self) -> IniContent {
         self.content.clone()
     }
@@ -572,6 +1630,7 @@ pub enum IniContent {
     Section(IniSection),
     Entry(IniEntry),
     Comment(IniComment),
+    Directive(IniDirective),
     Other(IniOther),
 }
 
@@ -581,6 +1640,7 @@ impl fmt::Display for IniContent {
            IniContent::Section(section) => write!(formatter, "{}", section),
            IniContent::Entry(entry)     => write!(formatter, "{}", entry),
            IniContent::Comment(comment) => write!(formatter, "{}", comment),
+           IniContent::Directive(directive) => write!(formatter, "{}", directive),
            IniContent::Other(other)     => write!(formatter, "{}", other),
        }
     }
@@ -618,6 +1678,49 @@ impl IniSection {
     }
 }
 
+impl IniSection {
+    fn create_subsection(name: &str, subsection: &str) -> IniSection {
+        let escaped_subsection = subsection.replace('\\', "\\\\").replace('"', "\\\"");
+        IniSection::create(&format!("{} \"{}\"", name, escaped_subsection))
+    }
+}
+
+impl IniSection {
+    fn get_subsection(&self) -> Option<(String, String)> { //name and subsection, e.g. [remote "origin"]
+        let text = self.name.trim();
+        let quote_index = text.find('"')?;
+        let name = text[..quote_index].trim_end();
+        if name.is_empty() || (name.len() == quote_index) || name.contains(char::is_whitespace) { return None; } //name must be a single word followed by whitespace
+
+        let mut subsection = String::new();
+        let mut is_escape = false;
+        let mut is_closed = false;
+        for c in text[quote_index + 1..].chars() {
+            if is_closed {
+                return None; //nothing is allowed after closing quote
+            } else if is_escape {
+                subsection.push(c);
+                is_escape = false;
+            } else if c == '\\' {
+                is_escape = true;
+            } else if c == '"' {
+                is_closed = true;
+            } else {
+                subsection.push(c);
+            }
+        }
+
+        if is_closed { Some((name.to_string(), subsection)) } else { None }
+    }
+
+    fn get_full_name(&self) -> String { //subsection is separated by dot, e.g. remote.origin
+        match self.get_subsection() {
+            Some((name, subsection)) => format!("{}.{}", name, subsection),
+            None                     => self.name.clone(),
+        }
+    }
+}
+
 impl IniSection {
     fn reformatted(&self) -> IniSection {
         IniSection {
@@ -649,73 +1752,121 @@ impl fmt::Display for IniSection {
 
 #[derive(Clone)]
 pub struct IniEntry {
-    prefix:    String,  // prefix
-    key:       String,  // key name
-    separator: String,  // separator (alongside any spacing)
-    value:     String,  // value
-    suffix:    String,  // suffix
+    prefix:    String,      // prefix
+    prelude:   String,      // keyword before key (e.g. export)
+    key:       String,      // key name
+    separator: String,      // separator (alongside any spacing)
+    value:     String,      // value
+    suffix:    String,      // suffix
+    escapes:   IniEscapes,  // quoting and escaping rules for value
 }
 
 impl IniEntry {
-    fn new(prefix: &Vec<char>, key: &Vec<char>, separator: &Vec<char>, value: &Vec<char>, suffix: &Vec<char>) -> IniEntry {
+    fn new(prefix: &Vec<char>, prelude: &Vec<char>, key: &Vec<char>, separator: &Vec<char>, value: &Vec<char>, suffix: &Vec<char>, escapes: IniEscapes) -> IniEntry {
         IniEntry {
             prefix:    prefix.into_iter().collect(),
+            prelude:   prelude.iter().collect(),
             key:       key.into_iter().collect(),
             separator: separator.into_iter().collect(),
             value:     value.into_iter().collect(),
             suffix:    suffix.into_iter().collect(),
+            escapes,
         }
     }
 
     fn create(key: &str, value: &str)-> IniEntry  {
         IniEntry {
             prefix:    String::new(),
+            prelude:   String::new(),
             key:       key.to_string(),
             separator: "=".to_string(),
             value:     value.to_string(),
             suffix:    String::new(),
+            escapes:   IniEscapes::Ini,
         }
     }
+
 }
 
 impl IniEntry {
     fn reformatted(&self) -> IniEntry {
         IniEntry {
             prefix:    String::new(),
+            prelude:   if self.prelude.is_empty() { String::new() } else { self.prelude.trim_end().to_string() + " " },
             key:       self.key.clone(),
-            separator: "=".to_string(),
+            separator: self.separator.trim().to_string(), //flag stays without separator
             value:     self.value.clone(),
             suffix:    String::new(),
+            escapes:   self.escapes,
         }
     }
 
     fn trimmed(&self) -> IniEntry {
         IniEntry {
             prefix:    String::new(),
+            prelude:   self.prelude.clone(),
             key:       self.key.clone(),
             separator: self.separator.clone(),
             value:     self.value.clone(),
             suffix:    String::new(),
+            escapes:   self.escapes,
         }
     }
 
     fn with_modified_value(&self, value: &str) -> IniEntry {
         IniEntry {
             prefix:    String::new(),
+            prelude:   self.prelude.clone(),
             key:       self.key.clone(),
-            separator: self.separator.clone(),
+            separator: if self.separator.is_empty() && !value.is_empty() { "=".to_string() } else { self.separator.clone() }, //flag needs separator once it has value
             value:     value.to_string(),
-            suffix:    String::new(),
+            suffix:    if self.suffix.trim().is_empty() { String::new() } else { self.suffix.clone() }, //inline comment is kept
+            escapes:   self.escapes,
         }
     }
 }
 
 impl IniEntry {
+    fn quoted(text: &str) -> String {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+}
+
+impl IniEntry {
+    pub fn get_key(&self) -> String {
+        match self.escapes {
+            IniEscapes::Reg => IniEntry::unquoted(&self.key),
+            _               => self.key.clone(),
+        }
+    }
+
     pub fn get_value(&self) -> &str {
         &self.value
     }
 
+    fn get_quote_char(&self) -> Option<char> { //quote value is enclosed in
+        let text = self.value.trim();
+        let quote_char = text.chars().next()?;
+        if (quote_char == '"' || quote_char == '\'') && (text.len() >= 2) && text.ends_with(quote_char) {
+            Some(quote_char)
+        } else {
+            None
+        }
+    }
+
     pub fn get_value_unquoted(&self) -> String {
+        match self.escapes {
+            IniEscapes::Ini     => self.get_value_unquoted_ini(),
+            IniEscapes::Dotenv  => self.get_value_unquoted_dotenv(),
+            IniEscapes::Systemd => self.get_value_unquoted_systemd(),
+            IniEscapes::Desktop => self.get_value_unquoted_desktop(),
+            IniEscapes::Reg     => self.get_value_unquoted_reg(),
+            IniEscapes::Git     => self.get_value_unquoted_git(),
+        }
+    }
+
+    fn get_value_unquoted_ini(&self) -> String {
         let text = self.get_value().trim();
 
         enum State { Quote, Text, Escape }
@@ -745,15 +1896,180 @@ impl IniEntry {
         }
         return new_text.into_iter().collect();
     }
+
+    fn get_value_unquoted_dotenv(&self) -> String {
+        let text = self.get_value().trim();
+
+        if text.len() >= 2 && text.starts_with('\'') && text.ends_with('\'') { //single quotes are taken literally
+            return text[1..text.len() - 1].to_string();
+        }
+
+        if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+            let mut new_text = Vec::new();
+            let mut is_escape = false;
+            for c in text[1..text.len() - 1].chars() {
+                if is_escape {
+                    match c {
+                        'n'                   => { new_text.push('\n'); },
+                        'r'                   => { new_text.push('\r'); },
+                        't'                   => { new_text.push('\t'); },
+                        '\\' | '"' | '\'' | '$' => { new_text.push(c); },
+                        _                     => { new_text.push('\\'); new_text.push(c); }, //unrecognized escape
+                    }
+                    is_escape = false;
+                } else if c == '\\' {
+                    is_escape = true;
+                } else {
+                    new_text.push(c);
+                }
+            }
+            if is_escape { new_text.push('\\'); }
+            return new_text.into_iter().collect();
+        }
+
+        text.to_string()
+    }
+
+    fn get_value_unquoted_systemd(&self) -> String {
+        let mut new_text = String::new();
+        for (index, line) in self.get_value().split('\n').enumerate() {
+            let line = line.trim_end_matches('\r');
+            if index > 0 {
+                let trimmed_line = line.trim_start();
+                if trimmed_line.starts_with('#') || trimmed_line.starts_with(';') { continue; } //comments within continuation are ignored
+            }
+            if let Some(line) = line.strip_suffix('\\') {
+                new_text.push_str(line);
+                new_text.push(' ');
+            } else {
+                new_text.push_str(line);
+            }
+        }
+        new_text.trim().to_string()
+    }
+
+    fn get_value_unquoted_desktop(&self) -> String {
+        let mut new_text = Vec::new();
+        let mut is_escape = false;
+        for c in self.get_value().trim().chars() {
+            if is_escape {
+                match c {
+                    's'  => { new_text.push(' '); },
+                    'n'  => { new_text.push('\n'); },
+                    't'  => { new_text.push('\t'); },
+                    'r'  => { new_text.push('\r'); },
+                    '\\' => { new_text.push('\\'); },
+                    _    => { new_text.push('\\'); new_text.push(c); }, //list separator and unrecognized escapes stay
+                }
+                is_escape = false;
+            } else if c == '\\' {
+                is_escape = true;
+            } else {
+                new_text.push(c);
+            }
+        }
+        if is_escape { new_text.push('\\'); }
+        new_text.into_iter().collect()
+    }
+
+    fn get_value_unquoted_reg(&self) -> String {
+        let text = self.get_value().trim();
+        if text.starts_with('"') {
+            return IniEntry::unquoted(text);
+        }
+
+        let mut new_text = String::new();
+        for line in text.split('\n') { //binary values are continued over multiple lines
+            let line = line.trim();
+            new_text.push_str(line.strip_suffix('\\').unwrap_or(line));
+        }
+        new_text
+    }
+
+    fn get_value_unquoted_git(&self) -> String {
+        let mut new_text = String::new();
+        let mut chars = self.get_value().trim().chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    Some('n')  => { new_text.push('\n'); },
+                    Some('t')  => { new_text.push('\t'); },
+                    Some('b')  => { new_text.push('\u{8}'); },
+                    Some('\r') if chars.peek() == Some(&'\n') => { chars.next(); }, //continued line
+                    Some('\n') => { },
+                    Some(c)    => { new_text.push(c); },
+                    None       => { },
+                }
+            } else if c != '"' { //quotes only protect whitespace and comment characters
+                new_text.push(c);
+            }
+        }
+        new_text
+    }
+
+    fn unquoted(text: &str) -> String { //only backslash and quote are escaped
+        if text.len() < 2 || !text.starts_with('"') || !text.ends_with('"') { return text.to_string(); }
+
+        let mut new_text = Vec::new();
+        let mut is_escape = false;
+        for c in text[1..text.len() - 1].chars() {
+            if is_escape {
+                new_text.push(c);
+                is_escape = false;
+            } else if c == '\\' {
+                is_escape = true;
+            } else {
+                new_text.push(c);
+            }
+        }
+        new_text.into_iter().collect()
+    }
+}
+
+impl IniEntry {
+    fn get_locale(&self) -> Option<(String, String)> { //key and locale, e.g. Name[de]
+        if self.key.ends_with(']') {
+            if let Some(bracket_index) = self.key.find('[') {
+                if (bracket_index > 0) && (bracket_index + 2 < self.key.len()) {
+                    return Some((self.key[..bracket_index].to_string(), self.key[bracket_index + 1..self.key.len() - 1].to_string()));
+                }
+            }
+        }
+        None
+    }
+
+    fn get_list_items(&self, separator: char) -> Vec<String> { //items are not unescaped
+        let mut items = Vec::new();
+        let mut item = String::new();
+        let mut is_escape = false;
+        for c in self.get_value().trim().chars() {
+            if is_escape {
+                item.push(c);
+                is_escape = false;
+            } else if c == '\\' {
+                item.push(c);
+                is_escape = true;
+            } else if c == separator {
+                items.push(item);
+                item = String::new();
+            } else {
+                item.push(c);
+            }
+        }
+        if !item.is_empty() { items.push(item); } //separator after the last item is optional
+        items
+    }
 }
 
 impl fmt::Display for IniEntry {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{}{}{}{}{}", self.prefix, self.key, self.separator, self.value, self.suffix)
+        write!(formatter, "{}{}{}{}{}{}", self.prefix, self.prelude, self.key, self.separator, self.value, self.suffix)
     }
 }
 
 
+const COMMENT_MARKER: &str = "inied:"; //start of comment text written by --comment so it can be updated later
+
 #[derive(Clone)]
 pub struct IniComment {
     prefix:  String,  // any leading whitespace
@@ -771,6 +2087,16 @@ impl IniComment {
     }
 }
 
+impl IniComment {
+    fn from_text(prelude: &str, text: &str) -> IniComment { //same style as reformatted comment
+        IniComment {
+            prefix:  String::new(),
+            prelude: prelude.to_string() + " ",
+            text:    text.trim().to_string(),
+        }
+    }
+}
+
 impl IniComment {
     fn reformatted(&self) -> IniComment {
         IniComment {
@@ -796,6 +2122,68 @@ impl fmt::Display for IniComment {
 }
 
 
+#[derive(Clone)]
+pub struct IniDirective {
+    prefix:    String,  // any leading whitespace
+    name:      String,  // directive name with exclamation mark (e.g. !include)
+    separator: String,  // whitespace between name and argument
+    argument:  String,  // directive argument (e.g. file name)
+    suffix:    String,  // any trailing whitespace
+}
+
+impl IniDirective {
+    fn parse(text: &str) -> Option<IniDirective> {
+        let content = text.trim_start();
+        if !content.starts_with('!') { return None; }
+        let prefix = &text[..text.len() - content.len()];
+
+        let content = content.trim_end();
+        let suffix = &text[prefix.len() + content.len()..];
+
+        let name_length = content.find(char::is_whitespace).unwrap_or(content.len());
+        let (name, remainder) = content.split_at(name_length);
+        let argument = remainder.trim_start();
+        let separator = &remainder[..remainder.len() - argument.len()];
+
+        Some(IniDirective {
+            prefix:    prefix.to_string(),
+            name:      name.to_string(),
+            separator: separator.to_string(),
+            argument:  argument.to_string(),
+            suffix:    suffix.to_string(),
+        })
+    }
+}
+
+impl IniDirective {
+    fn reformatted(&self) -> IniDirective {
+        IniDirective {
+            prefix:    String::new(),
+            name:      self.name.clone(),
+            separator: " ".to_string(),
+            argument:  self.argument.clone(),
+            suffix:    String::new(),
+        }
+    }
+
+    fn trimmed(&self) -> IniDirective {
+        IniDirective {
+            prefix:    String::new(),
+            name:      self.name.clone(),
+            separator: self.separator.clone(),
+            argument:  self.argument.clone(),
+            suffix:    String::new(),
+        }
+    }
+}
+
+impl fmt::Display for IniDirective {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}{}{}{}{}", self.prefix, self.name, self.separator, self.argument, self.suffix)
+    }
+}
+
+
 #[derive(Clone)]
 pub struct IniOther {
     text: String,  // any text
@@ -828,3 +2216,698 @@ impl fmt::Display for IniOther {
         write!(formatter, "{}", self.text)
     }
 }
+
+
+#[derive(Clone, PartialEq)]
+pub struct IniDialect {
+    sections:        bool,        // section headers are recognized
+    comment_chars:   Vec<String>, // text starting a comment (first one is used for new comments)
+    export_prefix:   bool,        // key can be preceded by export keyword
+    inline_comments: bool,        // comment can follow unquoted value
+    escapes:         IniEscapes,  // quoting and escaping rules for values
+    continuations:   bool,        // line ending with backslash continues on the next line
+    empty_resets:    bool,        // empty value resets all previous values of the same key
+    drop_ins:        bool,        // file can be extended by files in .d directory
+    list_separator:  Option<char>,// separator between list items
+    quoted_keys:     bool,        // key can be enclosed in quotes
+    headers:         Vec<&'static str>, // lines allowed at the start of file (e.g. REGEDIT4)
+    duplicates:      IniDuplicates, // which value is used when the same key appears multiple times
+    compare:         IniCompare,  // how section names and keys are matched
+    separators:      Vec<char>,   // characters separating key from value
+    key_spaces:      bool,        // key can contain whitespace within
+    flags:           bool,        // key without separator and value is an entry (e.g. skip-networking)
+    includes:        bool,        // !include and !includedir directives are recognized
+}
+
+impl IniDialect {
+    pub fn ini() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec![";".to_string(), "#".to_string()],
+            export_prefix:   false,
+            inline_comments: false,
+            escapes:         IniEscapes::Ini,
+            continuations:   false,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::LastWins,
+            compare:         IniCompare::Exact,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['=', ':'],
+            key_spaces:      true,
+        }
+    }
+
+    pub fn dotenv() -> IniDialect {
+        IniDialect {
+            sections:        false,
+            comment_chars:   vec!["#".to_string()],
+            export_prefix:   true,
+            inline_comments: true,
+            escapes:         IniEscapes::Dotenv,
+            continuations:   false,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::LastWins,
+            compare:         IniCompare::Exact,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn systemd() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec!["#".to_string(), ";".to_string()],
+            export_prefix:   false,
+            inline_comments: false,
+            escapes:         IniEscapes::Systemd,
+            continuations:   true,
+            empty_resets:    true,
+            drop_ins:        true,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::Allowed,
+            compare:         IniCompare::Exact,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn desktop() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec!["#".to_string()],
+            export_prefix:   false,
+            inline_comments: false,
+            escapes:         IniEscapes::Desktop,
+            continuations:   false,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  Some(';'),
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::LastWins,
+            compare:         IniCompare::Exact,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn reg() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec![";".to_string()],
+            export_prefix:   false,
+            inline_comments: false,
+            escapes:         IniEscapes::Reg,
+            continuations:   true,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     true,
+            headers:         vec!["REGEDIT4", "Windows Registry Editor Version 5.00"],
+            duplicates:      IniDuplicates::LastWins,
+            compare:         IniCompare::IgnoreCase,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn mysql() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec!["#".to_string(), ";".to_string()],
+            export_prefix:   false,
+            inline_comments: true,
+            escapes:         IniEscapes::Ini,
+            continuations:   false,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::LastWins,
+            compare:         IniCompare::MySql,
+            flags:           true,
+            includes:        true,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn php() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec![";".to_string()],
+            export_prefix:   false,
+            inline_comments: true,
+            escapes:         IniEscapes::Ini,
+            continuations:   false,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::LastWins,
+            compare:         IniCompare::IgnoreCase,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn windows() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec![";".to_string()],
+            export_prefix:   false,
+            inline_comments: false,
+            escapes:         IniEscapes::Ini,
+            continuations:   false,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::FirstWins,
+            compare:         IniCompare::IgnoreCase,
+            flags:           false,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      true,
+        }
+    }
+
+    pub fn gitconfig() -> IniDialect {
+        IniDialect {
+            sections:        true,
+            comment_chars:   vec!["#".to_string(), ";".to_string()],
+            export_prefix:   false,
+            inline_comments: true,
+            escapes:         IniEscapes::Git,
+            continuations:   true,
+            empty_resets:    false,
+            drop_ins:        false,
+            list_separator:  None,
+            quoted_keys:     false,
+            headers:         Vec::new(),
+            duplicates:      IniDuplicates::Allowed,
+            compare:         IniCompare::IgnoreCase,
+            flags:           true,
+            includes:        false,
+            separators:      vec!['='],
+            key_spaces:      false,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IniDialect> {
+        match name {
+            "ini"       => Some(IniDialect::ini()),
+            "dotenv"    => Some(IniDialect::dotenv()),
+            "systemd"   => Some(IniDialect::systemd()),
+            "desktop"   => Some(IniDialect::desktop()),
+            "reg"       => Some(IniDialect::reg()),
+            "mysql"     => Some(IniDialect::mysql()),
+            "php"       => Some(IniDialect::php()),
+            "windows"   => Some(IniDialect::windows()),
+            "gitconfig" => Some(IniDialect::gitconfig()),
+            _           => None,
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<IniDialect> { //dialect guessed from name or extension
+        let path = std::path::Path::new(file_name);
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+        let directory_name = path.parent().and_then(|directory| directory.file_name()).map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let is_systemd_drop_in = SYSTEMD_UNIT_EXTENSIONS.iter().any(|unit_extension| directory_name.ends_with(&format!(".{}.d", unit_extension))); //e.g. foo.service.d/override.conf
+
+        match (name.as_str(), extension.as_str()) {
+            (".env", _)                                          => Some(IniDialect::dotenv()),
+            (name, _) if name.starts_with(".env.")               => Some(IniDialect::dotenv()),
+            ("php.ini", _)                                       => Some(IniDialect::php()),
+            (name, "ini") if name.starts_with("php")             => Some(IniDialect::php()),
+            (_, "cnf")                                           => Some(IniDialect::mysql()),
+            (".gitconfig", _) | (".gitmodules", _)               => Some(IniDialect::gitconfig()),
+            ("config", _) if directory_name == ".git"            => Some(IniDialect::gitconfig()),
+            (_, "desktop")                                       => Some(IniDialect::desktop()),
+            (_, "reg")                                           => Some(IniDialect::reg()),
+            (_, "conf") if is_systemd_drop_in                    => Some(IniDialect::systemd()),
+            (_, extension) if SYSTEMD_UNIT_EXTENSIONS.contains(&extension) => Some(IniDialect::systemd()),
+            _                                                    => None,
+        }
+    }
+}
+
+impl IniDialect {
+    fn encoded_value(&self, value: &str, quote_char: char, always_quote: bool) -> String { //inverse of unquoting
+        match self.escapes {
+            IniEscapes::Ini => {
+                let has_comment = value.char_indices().any(|(index, _)| self.comment_length(&value[index..]).is_some()); //other parsers might see comment
+                let needs_quotes = always_quote || has_comment || (value.trim() != value) || value.starts_with('"') || value.starts_with('\'') || value.starts_with('\\') || value.contains('\n') || value.contains('\r'); //leading backslash is not an escape without quotes
+                let mut text = String::new();
+                if needs_quotes {
+                    text.push(quote_char);
+                    for c in value.chars() {
+                        match c {
+                            '\\' => { text.push_str("\\\\"); },
+                            '\n' => { text.push_str("\\n"); },
+                            '\r' => { text.push_str("\\r"); },
+                            _ if c == quote_char => { text.push('\\'); text.push(c); },
+                            _    => { text.push(c); },
+                        }
+                    }
+                    text.push(quote_char);
+                } else {
+                    let mut chars = value.chars().peekable();
+                    while let Some(c) = chars.next() {
+                        let is_escape = match chars.peek() { //backslash is kept as is unless it would start an escape
+                            Some(next) => c == '\\' && "\\'\"nrt".contains(*next),
+                            None       => c == '\\',
+                        };
+                        if is_escape { text.push('\\'); }
+                        text.push(c);
+                    }
+                }
+                text
+            },
+            IniEscapes::Dotenv => {
+                let needs_escapes = value.contains('\n') || value.contains('\r');
+                let has_expansion = value.contains('$') || value.contains('`'); //shell would expand these within double quotes
+                let has_shell_chars = value.contains(|c: char| c.is_whitespace() || "\"'\\#;&|<>()*?[]{}~!".contains(c)); //file might be sourced by shell
+                let needs_quotes = always_quote || needs_escapes || has_expansion || has_shell_chars;
+                if !needs_quotes {
+                    value.to_string()
+                } else if (quote_char == '\'' || has_expansion) && !needs_escapes && !value.contains('\'') { //single quotes are literal
+                    format!("'{}'", value)
+                } else {
+                    let mut text = String::new();
+                    text.push('"');
+                    for c in value.chars() {
+                        match c {
+                            '\\' => { text.push_str("\\\\"); },
+                            '"'  => { text.push_str("\\\""); },
+                            '$'  => { text.push_str("\\$"); },
+                            '\n' => { text.push_str("\\n"); },
+                            '\r' => { text.push_str("\\r"); },
+                            _    => { text.push(c); },
+                        }
+                    }
+                    text.push('"');
+                    text
+                }
+            },
+            IniEscapes::Desktop => {
+                let trimmed_start = value.trim_start_matches(' ');
+                let trimmed = trimmed_start.trim_end_matches(' ');
+                let mut text = "\\s".repeat(value.len() - trimmed_start.len()); //spaces at either end would be trimmed
+                for c in trimmed.chars() {
+                    match c {
+                        '\\' => { text.push_str("\\\\"); },
+                        '\n' => { text.push_str("\\n"); },
+                        '\t' => { text.push_str("\\t"); },
+                        '\r' => { text.push_str("\\r"); },
+                        _    => { text.push(c); },
+                    }
+                }
+                text.push_str(&"\\s".repeat(trimmed_start.len() - trimmed.len()));
+                text
+            },
+            IniEscapes::Reg => {
+                let is_typed = value.starts_with("dword:") || value.starts_with("hex:") || value.starts_with("hex(");
+                if is_typed || value == "-" { value.to_string() } else { IniEntry::quoted(value) } //everything else is a string
+            },
+            IniEscapes::Git => {
+                let has_comment = value.char_indices().any(|(index, _)| self.comment_length(&value[index..]).is_some());
+                let needs_quotes = always_quote || has_comment || (value.trim() != value);
+                let mut text = String::new();
+                if needs_quotes { text.push('"'); }
+                for c in value.chars() {
+                    match c {
+                        '\\' => { text.push_str("\\\\"); },
+                        '"'  => { text.push_str("\\\""); },
+                        '\n' => { text.push_str("\\n"); },
+                        '\t' => { text.push_str("\\t"); },
+                        _    => { text.push(c); },
+                    }
+                }
+                if needs_quotes { text.push('"'); }
+                text
+            },
+            IniEscapes::Systemd => value.to_string(), //quotes have meaning for command itself
+        }
+    }
+}
+
+impl IniDialect {
+    fn comment_length(&self, text: &str) -> Option<usize> { //number of characters starting a comment
+        let mut comment_length = None;
+        for comment_chars in &self.comment_chars {
+            if comment_chars.is_empty() || !text.starts_with(comment_chars.as_str()) { continue; }
+
+            let is_word = comment_chars.chars().last().is_some_and(char::is_alphanumeric);
+            let is_word_continued = text[comment_chars.len()..].chars().next().is_some_and(char::is_alphanumeric);
+            if is_word && is_word_continued { continue; } //e.g. REM doesn't start REMOTE
+
+            comment_length = comment_length.max(Some(comment_chars.chars().count()));
+        }
+        comment_length
+    }
+
+    pub fn set_comment_chars(&mut self, comment_chars: Vec<String>) {
+        self.comment_chars = comment_chars;
+    }
+}
+
+impl IniDialect {
+    pub fn has_sections(&self) -> bool {
+        self.sections
+    }
+
+    pub fn has_drop_ins(&self) -> bool {
+        self.drop_ins
+    }
+
+    pub fn has_includes(&self) -> bool {
+        self.includes
+    }
+
+    pub fn has_flags(&self) -> bool {
+        self.flags
+    }
+
+    pub fn get_duplicates(&self) -> IniDuplicates {
+        self.duplicates
+    }
+
+    pub fn is_valid_key(&self, key: &str) -> bool { //separator within key would split it when read back
+        self.quoted_keys || !key.chars().any(|c| self.separators.contains(&c))
+    }
+
+    pub fn has_lists(&self) -> bool {
+        self.list_separator.is_some()
+    }
+
+    pub fn set_ignore_case(&mut self) {
+        if self.compare == IniCompare::Exact { self.compare = IniCompare::IgnoreCase; }
+    }
+}
+
+
+#[derive(Clone, Default)]
+pub struct IniEditOptions {
+    pub raw:              bool,                // values are written as given, without quoting or escaping
+    pub comment:          Option<String>,      // comment to add above new or changed entry
+    pub position:         IniPosition,         // where new entry goes within section
+    pub section_position: IniSectionPosition,  // where new section goes within file
+}
+
+#[derive(Clone, Default)]
+pub enum IniPosition {
+    #[default]
+    Last,            // at the end of section
+    First,           // before the first entry in section
+    Before(String),  // before the first entry with given key
+    After(String),   // after the last entry with given key
+}
+
+#[derive(Clone, Default)]
+pub enum IniSectionPosition {
+    #[default]
+    Last,            // at the end of file
+    Before(String),  // before the section with given name
+    After(String),   // after the section with given name
+    Sorted,          // before the first section that sorts after it
+}
+
+
+const SYSTEMD_UNIT_EXTENSIONS: &[&str] = &["service", "socket", "device", "mount", "automount", "swap", "target", "path", "timer", "slice", "scope", "network", "netdev", "link"];
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IniDuplicates {
+    Allowed,    // all values are used (e.g. lists)
+    FirstWins,  // the first value is used
+    LastWins,   // the last value is used
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IniDedupe {
+    KeepFirst,  // the first value is kept
+    KeepLast,   // the last value is kept, as most parsers do
+    Error,      // nothing is removed but duplicates are reported as errors
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IniEscapes {
+    Ini,      // both single and double quotes allow backslash escapes
+    Dotenv,   // single quotes are literal, double quotes allow backslash escapes
+    Systemd,  // no quoting, continued lines are joined with space
+    Desktop,  // no quoting, backslash escapes (including \s for space)
+    Reg,      // quoted strings with backslash escapes, typed values are not quoted
+    Git,      // double quotes anywhere within value, backslash escapes
+}
+
+
+const UTF8_BOM: &[u8]    = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IniCompare {
+    Exact,       // names must be the same
+    IgnoreCase,  // ASCII letters are compared without regard to case
+    MySql,       // case is ignored, dash and underscore are the same, and loose- prefix is ignored in keys
+}
+
+impl IniCompare {
+    fn normalized(&self, text: &str) -> String {
+        match self {
+            IniCompare::Exact      => text.to_string(),
+            IniCompare::IgnoreCase => text.to_ascii_lowercase(),
+            IniCompare::MySql      => {
+                let text = text.to_ascii_lowercase().replace('-', "_");
+                match text.strip_prefix("loose_") {
+                    Some(text) => text.to_string(),
+                    None       => text,
+                }
+            },
+        }
+    }
+
+    fn normalized_section(&self, text: &str) -> String {
+        match self {
+            IniCompare::Exact      => text.to_string(),
+            IniCompare::IgnoreCase => text.to_ascii_lowercase(),
+            IniCompare::MySql      => text.to_ascii_lowercase(), //group names are not affected by option rules
+        }
+    }
+
+    fn is_match(&self, text: &str, other_text: &str) -> bool {
+        self.normalized(text) == self.normalized(other_text)
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IniEncoding {
+    Utf8,     // no byte order mark
+    Utf8Bom,  // with byte order mark
+    Utf16Le,  // little endian, always saved with byte order mark (e.g. Windows registry files)
+    Utf16Be,  // big endian, always saved with byte order mark
+}
+
+impl IniEncoding {
+    pub fn from_name(name: &str) -> Option<IniEncoding> {
+        match name {
+            "utf-8"     => Some(IniEncoding::Utf8),
+            "utf-8-bom" => Some(IniEncoding::Utf8Bom),
+            "utf-16le"  => Some(IniEncoding::Utf16Le),
+            "utf-16be"  => Some(IniEncoding::Utf16Be),
+            _           => None,
+        }
+    }
+
+    fn detect(bytes: &[u8]) -> IniEncoding {
+        if bytes.starts_with(UTF8_BOM) {
+            IniEncoding::Utf8Bom
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            IniEncoding::Utf16Le
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            IniEncoding::Utf16Be
+        } else {
+            IniEncoding::Utf8
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<String, IniError> { //byte order mark is optional
+        let pairs = match self {
+            IniEncoding::Utf8 | IniEncoding::Utf8Bom => {
+                return Ok(from_bytes_lossless(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)));
+            },
+            IniEncoding::Utf16Le => bytes.strip_prefix(UTF16LE_BOM).unwrap_or(bytes).chunks_exact(2),
+            IniEncoding::Utf16Be => bytes.strip_prefix(UTF16BE_BOM).unwrap_or(bytes).chunks_exact(2),
+        };
+        let has_odd_byte = !pairs.remainder().is_empty();
+        let units = pairs.map(|pair| {
+            match self {
+                IniEncoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+                _                    => u16::from_le_bytes([pair[0], pair[1]]),
+            }
+        });
+
+        let mut text = String::new();
+        for c in std::char::decode_utf16(units) {
+            match c {
+                Ok(c)  => { text.push(c); },
+                Err(_) => {
+                    let (line_number, column) = text_position(&text);
+                    return Err(IniError::encoding(line_number, column, "unpaired UTF-16 surrogate"));
+                },
+            }
+        }
+        if has_odd_byte {
+            let (line_number, column) = text_position(&text);
+            return Err(IniError::encoding(line_number, column, "incomplete UTF-16 character"));
+        }
+        Ok(text)
+    }
+
+    fn encode(&self, text: &str) -> Result<Vec<u8>, IniError> {
+        let utf8_bytes = to_bytes_lossless(text);
+        match self {
+            IniEncoding::Utf8    => Ok(utf8_bytes),
+            IniEncoding::Utf8Bom => Ok([UTF8_BOM, &utf8_bytes].concat()),
+            IniEncoding::Utf16Le => IniEncoding::utf16_bytes(utf8_bytes, UTF16LE_BOM, u16::to_le_bytes),
+            IniEncoding::Utf16Be => IniEncoding::utf16_bytes(utf8_bytes, UTF16BE_BOM, u16::to_be_bytes),
+        }
+    }
+
+    fn utf16_bytes(utf8_bytes: Vec<u8>, bom: &[u8], to_bytes: fn(u16) -> [u8; 2]) -> Result<Vec<u8>, IniError> {
+        let text = match String::from_utf8(utf8_bytes) {
+            Ok(text) => text,
+            Err(error) => {
+                let (line_number, column) = text_position(&from_bytes_lossless(&error.as_bytes()[..error.utf8_error().valid_up_to()]));
+                return Err(IniError::encoding(line_number, column, "invalid UTF-8 byte cannot be converted to UTF-16"));
+            },
+        };
+        let mut bytes = bom.to_vec();
+        for unit in text.encode_utf16() { bytes.extend_from_slice(&to_bytes(unit)); }
+        Ok(bytes)
+    }
+}
+
+
+#[derive(Debug)]
+pub enum IniError {
+    Io       { file_name: Option<String>, error: Error },                                        // file cannot be read or written
+    Encoding { file_name: Option<String>, line_number: usize, column: usize, message: String },  // content cannot be decoded or encoded
+    Syntax   { file_name: Option<String>, line_number: usize, column: usize, message: String },  // line is not valid (strict mode only)
+}
+
+impl IniError {
+    fn io(file_name: Option<&str>, error: Error) -> IniError {
+        IniError::Io { file_name: file_name.map(|file_name| file_name.to_string()), error }
+    }
+
+    fn encoding(line_number: usize, column: usize, message: &str) -> IniError {
+        IniError::Encoding { file_name: None, line_number, column, message: message.to_string() }
+    }
+
+    fn syntax(line_number: usize, column: usize, message: &str) -> IniError {
+        IniError::Syntax { file_name: None, line_number, column, message: message.to_string() }
+    }
+
+    fn with_file_name(self, new_file_name: Option<&str>) -> IniError {
+        let new_file_name = new_file_name.map(|file_name| file_name.to_string());
+        match self {
+            IniError::Io { error, .. }                              => IniError::Io { file_name: new_file_name, error },
+            IniError::Encoding { line_number, column, message, .. } => IniError::Encoding { file_name: new_file_name, line_number, column, message },
+            IniError::Syntax { line_number, column, message, .. }   => IniError::Syntax { file_name: new_file_name, line_number, column, message },
+        }
+    }
+}
+
+impl IniError {
+    pub fn get_file_name(&self) -> Option<&str> {
+        match self {
+            IniError::Io { file_name, .. }       => file_name.as_deref(),
+            IniError::Encoding { file_name, .. } => file_name.as_deref(),
+            IniError::Syntax { file_name, .. }   => file_name.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for IniError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IniError::Io { error, .. } => write!(formatter, "{}", error),
+            IniError::Encoding { line_number, column, message, .. } | IniError::Syntax { line_number, column, message, .. } => {
+                match self.get_file_name() {
+                    Some(file_name) => write!(formatter, "{}:{}:{}: {}", file_name, line_number, column, message),
+                    None            => write!(formatter, "{}:{}: {}", line_number, column, message),
+                }
+            },
+        }
+    }
+}
+
+
+pub struct IniIssue {
+    line_number: usize,        // line number where issue was found
+    column:      usize,        // column where issue was found
+    severity:    IniSeverity,  // how serious is the issue
+    message:     String,       // description
+}
+
+impl IniIssue {
+    fn error(line_number: usize, column: usize, message: &str) -> IniIssue {
+        IniIssue { line_number, column, severity: IniSeverity::Error, message: message.to_string() }
+    }
+
+    fn warning(line_number: usize, column: usize, message: &str) -> IniIssue {
+        IniIssue { line_number, column, severity: IniSeverity::Warning, message: message.to_string() }
+    }
+}
+
+impl fmt::Display for IniIssue {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}:{}: {}: {}", self.line_number, self.column, self.severity, self.message)
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IniSeverity {
+    Error,    // line cannot be parsed
+    Warning,  // line is valid but probably not what was intended
+}
+
+impl fmt::Display for IniSeverity {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IniSeverity::Error   => write!(formatter, "error"),
+            IniSeverity::Warning => write!(formatter, "warning"),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+use std::path::PathBuf;
+
+
+pub const MAX_INCLUDE_DEPTH: usize = 10; //same limit as used by MySQL
+
+pub fn include_path(file_name: &str, include_name: &str) -> PathBuf { //relative paths are resolved from directory of including file
+    let include_path = Path::new(include_name);
+    match Path::new(file_name).parent() {
+        Some(directory) if include_path.is_relative() => directory.join(include_path),
+        _                                             => include_path.to_path_buf(),
+    }
+}
+
+pub fn include_dir_files(file_name: &str, directory_name: &str) -> Result<Vec<PathBuf>, Error> {
+    let directory = include_path(file_name, directory_name);
+    if !directory.is_dir() { return Ok(Vec::new()); } //missing directory is not an error
+
+    let mut files = Vec::new();
+    for dir_entry in fs::read_dir(&directory)? {
+        let path = dir_entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "cnf") {
+            files.push(path);
+        }
+    }
+    files.sort(); //MySQL doesn't guarantee order; sorting by name keeps it predictable
+    Ok(files)
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+use std::path::PathBuf;
+
+
+pub fn drop_in_files(unit_file_name: &str) -> Result<Vec<PathBuf>, Error> {
+    let unit_path = Path::new(unit_file_name);
+    let unit_directory = match unit_path.parent() {
+        Some(directory) => directory.to_path_buf(),
+        None            => PathBuf::new(),
+    };
+    let unit_name = match unit_path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None       => return Ok(Vec::new()),
+    };
+
+    let mut drop_ins = BTreeMap::new(); //sorted by file name; more specific directory overrides the same file name
+    for directory_name in drop_in_directories(&unit_name) {
+        let directory = unit_directory.join(directory_name);
+        if !directory.is_dir() { continue; }
+
+        for dir_entry in fs::read_dir(&directory)? {
+            let path = dir_entry?.path();
+            if path.is_file() && path.extension().is_some_and(|extension| extension == "conf") {
+                if let Some(file_name) = path.file_name() {
+                    drop_ins.insert(file_name.to_os_string(), path.clone());
+                }
+            }
+        }
+    }
+
+    Ok(drop_ins.into_values().collect())
+}
+
+pub fn drop_in_directories(unit_name: &str) -> Vec<String> { //from the least to the most specific
+    let mut directories = Vec::new();
+    if let Some(type_index) = unit_name.rfind('.') {
+        let (unit_stem, unit_type) = unit_name.split_at(type_index); //unit_type includes dot
+
+        directories.push(unit_type[1..].to_string() + ".d"); //e.g. service.d
+        for (index, c) in unit_stem.char_indices() { //e.g. foo-.service.d for foo-bar.service
+            if c == '-' && index + 1 < unit_stem.len() {
+                directories.push(unit_stem[..=index].to_string() + unit_type + ".d");
+            }
+        }
+        if let Some(at_index) = unit_stem.find('@') { //e.g. foo@.service.d for foo@bar.service
+            if at_index + 1 < unit_stem.len() {
+                directories.push(unit_stem[..=at_index].to_string() + unit_type + ".d");
+            }
+        }
+    }
+    directories.push(unit_name.to_string() + ".d");
+    directories
+}
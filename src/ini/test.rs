@@ -1,3 +1,9 @@
+impl super::IniLine {
+    fn new(raw_content: &str, line_ending: &str) -> super::IniLine {
+        super::IniLine::parse(raw_content, line_ending, &super::IniDialect::ini())
+    }
+}
+
 impl super::IniComment {
     fn create(prelude: &str, text: &str) -> super::IniComment {
         super::IniComment {
@@ -208,6 +214,56 @@ fn parse_entry_double_inner_space() {
     }
 }
 
+#[test]
+fn parse_entry_key_with_spaces() {
+    let parsed = super::IniLine::new("  Name With  Space  =  1 ", "");
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("  Name With  Space  =  1 ", entry.to_string());
+            assert_eq!("  ", entry.prefix);
+            assert_eq!("Name With  Space", entry.key);
+            assert_eq!("  =  ", entry.separator);
+            assert_eq!("1", entry.value);
+            assert_eq!(" ", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_entry_key_with_spaces_not_in_systemd() {
+    let parsed = super::IniLine::parse("Name With Space=1", "", &super::IniDialect::systemd());
+    match parsed.content {
+        super::IniContent::Other(_) => {},
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_entry_colon_separator() {
+    let parsed = super::IniLine::new("Start Time: 10:00 = x", "");
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Start Time", entry.key);
+            assert_eq!(": ", entry.separator);
+            assert_eq!("10:00 = x", entry.value);
+            assert_eq!("Start Time:10:00 = x", entry.reformatted().to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_entry_flag_reformatted() {
+    let parsed = super::IniLine::parse("  Key  ", "", &super::IniDialect::mysql());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Key", entry.reformatted().to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
 #[test]
 fn parse_entry_reformatted() {
     let parsed = super::IniLine::new("    Key = Value    ", "");
@@ -570,6 +626,53 @@ fn parse_comment_trimmed() {
 }
 
 
+#[test]
+fn parse_comment_custom_chars() {
+    let mut dialect = super::IniDialect::ini();
+    dialect.set_comment_chars(vec!["//".to_string(), "REM".to_string()]);
+
+    match super::IniLine::parse("  // Comment", "", &dialect).content {
+        super::IniContent::Comment(comment) => {
+            assert_eq!("  // Comment", comment.to_string());
+            assert_eq!("//",           comment.prelude);
+            assert_eq!(" Comment",     comment.text);
+            assert_eq!("// Comment",   comment.reformatted().to_string());
+        },
+        _ => panic!("failed match"),
+    }
+    match super::IniLine::parse("REM Comment", "", &dialect).content {
+        super::IniContent::Comment(comment) => {
+            assert_eq!("REM",      comment.prelude);
+            assert_eq!(" Comment", comment.text);
+        },
+        _ => panic!("failed match"),
+    }
+    match super::IniLine::parse("REMOTE=1", "", &dialect).content {
+        super::IniContent::Entry(entry) => { assert_eq!("REMOTE", entry.key); },
+        _ => panic!("failed match"),
+    }
+    match super::IniLine::parse("#Key=1", "", &dialect).content {
+        super::IniContent::Entry(entry) => { assert_eq!("#Key", entry.key); },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_comment_custom_chars_inline() {
+    let mut dialect = super::IniDialect::mysql();
+    dialect.set_comment_chars(vec!["//".to_string()]);
+
+    match super::IniLine::parse("Key=a/b // Comment", "", &dialect).content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("a/b",           entry.value);
+            assert_eq!(" // Comment",   entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+    assert_eq!("\"a // b\"", dialect.encoded_value("a // b", '"', false));
+    assert_eq!("a # b",      dialect.encoded_value("a # b", '"', false));
+}
+
 #[test]
 fn parse_other_empty() {
     let parsed = super::IniLine::new("", "");
@@ -656,6 +759,36 @@ fn parse_other_starts_with_equals_and_spaces() {
     }
 }
 
+#[test]
+fn parse_entry_flag() {
+    let parsed = super::IniLine::parse("Key", "", &super::IniDialect::mysql());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Key", entry.to_string());
+            assert_eq!("Key", entry.key);
+            assert_eq!("",    entry.separator);
+            assert_eq!("",    entry.value);
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_entry_flag_with_spaces() {
+    let parsed = super::IniLine::parse("  Key  ", "", &super::IniDialect::mysql());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("  Key  ", entry.to_string());
+            assert_eq!("  ",      entry.prefix);
+            assert_eq!("Key",     entry.key);
+            assert_eq!("",        entry.separator);
+            assert_eq!("  ",      entry.suffix);
+            assert_eq!("Key",     entry.trimmed().to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
 #[test]
 fn parse_other_unfinished_entry() {
     let parsed = super::IniLine::new("Key", "");
@@ -694,413 +827,2255 @@ fn parse_other_looks_like_entry() {
 
 
 #[test]
-fn pretty_print_adds_empty_line() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),  line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),  line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+fn parse_dotenv_basic() {
+    let parsed = super::IniLine::parse("Key=Value", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Key=Value", entry.to_string());
+            assert_eq!("Value", entry.get_value_unquoted());
+            assert_eq!("", entry.prefix);
+            assert_eq!("", entry.prelude);
+            assert_eq!("Key", entry.key);
+            assert_eq!("=", entry.separator);
+            assert_eq!("Value", entry.value);
+            assert_eq!("", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    file.reformat();
-    let file = file;
-    print(&file);
+#[test]
+fn parse_dotenv_export() {
+    let parsed = super::IniLine::parse("  export  Key=Value", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("  export  Key=Value", entry.to_string());
+            assert_eq!("Value", entry.get_value_unquoted());
+            assert_eq!("  ", entry.prefix);
+            assert_eq!("export  ", entry.prelude);
+            assert_eq!("Key", entry.key);
+            assert_eq!("=", entry.separator);
+            assert_eq!("Value", entry.value);
+            assert_eq!("", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    assert_eq!("[X]", file.lines[0].content.to_string());
-    assert_eq!("A=1", file.lines[1].content.to_string());
-    assert_eq!("",    file.lines[2].content.to_string());
-    assert_eq!("[Y]", file.lines[3].content.to_string());
-    assert_eq!("B=2", file.lines[4].content.to_string());
+#[test]
+fn parse_dotenv_export_as_key() {
+    let parsed = super::IniLine::parse("export = Value", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("export = Value", entry.to_string());
+            assert_eq!("", entry.prelude);
+            assert_eq!("export", entry.key);
+            assert_eq!(" = ", entry.separator);
+            assert_eq!("Value", entry.value);
+        },
+        _ => panic!("failed match"),
+    }
 }
 
 #[test]
-fn pretty_print_removes_empty_line() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),  line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),  line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+fn parse_dotenv_export_without_value() {
+    let parsed = super::IniLine::parse("export Key", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Other(other) => {
+            assert_eq!("export Key", other.to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    file.reformat();
-    let file = file;
-    print(&file);
+#[test]
+fn parse_dotenv_inline_comment() {
+    let parsed = super::IniLine::parse("Key=Value With Spaces  # Comment", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Key=Value With Spaces  # Comment", entry.to_string());
+            assert_eq!("Value With Spaces", entry.get_value_unquoted());
+            assert_eq!("Key", entry.key);
+            assert_eq!("Value With Spaces", entry.value);
+            assert_eq!("  # Comment", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    assert_eq!("[X]", file.lines[0].content.to_string());
-    assert_eq!("A=1", file.lines[1].content.to_string());
-    assert_eq!("",    file.lines[2].content.to_string());
-    assert_eq!("[Y]", file.lines[3].content.to_string());
-    assert_eq!("B=2", file.lines[4].content.to_string());
+#[test]
+fn parse_dotenv_inline_comment_empty_value() {
+    let parsed = super::IniLine::parse("Key= # Comment", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Key= # Comment", entry.to_string());
+            assert_eq!("", entry.get_value_unquoted());
+            assert_eq!("= ", entry.separator);
+            assert_eq!("", entry.value);
+            assert_eq!("# Comment", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
 }
 
 #[test]
-fn filter_section() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+fn parse_dotenv_hash_inside_value() {
+    let parsed = super::IniLine::parse("Key=Value#NoComment", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Value#NoComment", entry.get_value_unquoted());
+            assert_eq!("", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    file.filter(Some("X"), None);
-    let file = file;
-    print(&file);
+#[test]
+fn parse_dotenv_single_quoted() {
+    let parsed = super::IniLine::parse("Key='  Value # \\n \\'  # Comment", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("'  Value # \\n \\'", entry.get_value());
+            assert_eq!("  Value # \\n \\", entry.get_value_unquoted());
+            assert_eq!("  # Comment", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    assert_eq!(2,      file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=1",  file.lines[1].content.to_string());
+#[test]
+fn parse_dotenv_double_quoted() {
+    let parsed = super::IniLine::parse("Key=\"  Value # \\t \\n \\\" \\$ \\q \"  # Comment", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("\"  Value # \\t \\n \\\" \\$ \\q \"", entry.get_value());
+            assert_eq!("  Value # \t \n \" $ \\q ", entry.get_value_unquoted());
+            assert_eq!("  # Comment", entry.suffix);
+        },
+        _ => panic!("failed match"),
+    }
 }
 
 #[test]
-fn filter_entry_found() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+fn parse_dotenv_comment() {
+    let parsed = super::IniLine::parse("# Test", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Comment(comment) => {
+            assert_eq!("# Test", comment.to_string());
+            assert_eq!("#", comment.prelude);
+            assert_eq!(" Test", comment.text);
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    file.filter(Some("X"), Some("A"));
-    let file = file;
-    print(&file);
+#[test]
+fn parse_dotenv_no_section() {
+    let parsed = super::IniLine::parse("[Test]", "", &super::IniDialect::dotenv());
+    match parsed.content {
+        super::IniContent::Other(other) => {
+            assert_eq!("[Test]", other.to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
 
-    assert_eq!(1,      file.lines.len());
-    assert_eq!("A=1",  file.lines[0].content.to_string());
+
+#[test]
+fn parse_systemd_continuation() {
+    let parsed = super::IniLine::parse("ExecStart=/bin/foo \\\n  --bar \\\r\n# Comment\n  --baz", "\n", &super::IniDialect::systemd());
+    assert!(!parsed.is_continued());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("ExecStart", entry.key);
+            assert_eq!("/bin/foo \\\n  --bar \\\r\n# Comment\n  --baz", entry.value);
+            assert_eq!("/bin/foo    --bar    --baz", entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
 }
 
 #[test]
-fn filter_entry_section_not_found() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+fn parse_systemd_unfinished_continuation() {
+    let parsed = super::IniLine::parse("ExecStart=/bin/foo \\", "\n", &super::IniDialect::systemd());
+    assert!(parsed.is_continued());
+}
 
-    file.filter(Some("-"), Some("A"));
-    let file = file;
+#[test]
+fn parse_file_unfinished_continuation() {
+    let directory = create_files("continuation", &[("a.service", "[Service]\nExecStart=/a \\\n"), ("b.reg", "[A]\n\"B\"=hex:01,\\\n")]);
+
+    let file_name = directory.join("a.service");
+    let file = super::IniFile::parse(file_name.to_str(), &super::IniDialect::systemd(), None, false).unwrap_or_else(|err| panic!("{}", err));
     print(&file);
 
-    assert_eq!(0,      file.lines.len());
-}
+    assert_eq!(2,                file.lines.len());
+    assert_eq!("ExecStart=/a \\", file.lines[1].content.to_string());
+    assert_eq!("\n",             file.lines[1].line_ending);
 
-#[test]
-fn filter_entry_key_not_found() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    match super::IniFile::parse(file_name.to_str(), &super::IniDialect::systemd(), None, true) {
+        Err(err) => { assert_eq!(format!("{}:2:14: continued line is not finished", file_name.display()), err.to_string()); },
+        Ok(_)    => panic!("unfinished continuation not reported"),
+    }
 
-    file.filter(Some("X"), Some("-"));
-    let file = file;
+    let file_name = directory.join("b.reg");
+    let file = super::IniFile::parse(file_name.to_str(), &super::IniDialect::reg(), None, false).unwrap_or_else(|err| panic!("{}", err));
     print(&file);
 
-    assert_eq!(0,      file.lines.len());
+    assert_eq!(2,                file.lines.len());
+    assert_eq!("\"B\"=hex:01,\\", file.lines[1].content.to_string());
 }
 
 #[test]
-fn append_no_spacing() {
-    let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+fn parse_systemd_no_unquote() {
+    let parsed = super::IniLine::parse("ExecStart=/bin/echo \"Value\"", "", &super::IniDialect::systemd());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("/bin/echo \"Value\"", entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+
+#[test]
+fn parse_section_subsection() {
+    let parsed = super::IniLine::new("[remote \"origin\"]", "");
+    match parsed.content {
+        super::IniContent::Section(section) => {
+            assert_eq!("[remote \"origin\"]", section.to_string());
+            assert_eq!("remote \"origin\"", section.name);
+            assert_eq!(Some(("remote".to_string(), "origin".to_string())), section.get_subsection());
+            assert_eq!("remote.origin", section.get_full_name());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_section_subsection_escaped() {
+    let parsed = super::IniLine::new("[branch  \"a\\\\b\\\"c.d\" ]", "");
+    match parsed.content {
+        super::IniContent::Section(section) => {
+            assert_eq!(Some(("branch".to_string(), "a\\b\"c.d".to_string())), section.get_subsection());
+            assert_eq!("branch.a\\b\"c.d", section.get_full_name());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_section_subsection_invalid() {
+    for text in &["[remote \"origin]", "[remote \"origin\" x]", "[\"origin\"]", "[a b \"origin\"]", "[remote.origin]"] {
+        let parsed = super::IniLine::new(text, "");
+        match parsed.content {
+            super::IniContent::Section(section) => {
+                assert_eq!(None, section.get_subsection());
+                assert_eq!(section.name, section.get_full_name());
+            },
+            _ => panic!("failed match"),
+        }
+    }
+}
+
+
+#[test]
+fn parse_desktop_localized() {
+    let parsed = super::IniLine::parse("Name[sr@latin]=Datoteke", "", &super::IniDialect::desktop());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("Name[sr@latin]", entry.key);
+            assert_eq!(Some(("Name".to_string(), "sr@latin".to_string())), entry.get_locale());
+            assert_eq!("Datoteke", entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_desktop_not_localized() {
+    for text in &["Name=X", "Name[de=X", "Name[]=X"] {
+        let parsed = super::IniLine::parse(text, "", &super::IniDialect::desktop());
+        match parsed.content {
+            super::IniContent::Entry(entry) => { assert_eq!(None, entry.get_locale()); },
+            _ => panic!("failed match"),
+        }
+    }
+}
+
+#[test]
+fn parse_desktop_escapes() {
+    let parsed = super::IniLine::parse("Comment=\"A\"\\sB\\tC\\nD\\\\E\\;F\\q", "", &super::IniDialect::desktop());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("\"A\" B\tC\nD\\E\\;F\\q", entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_desktop_list() {
+    let parsed = super::IniLine::parse("Keywords=A;B\\;C;;D;", "", &super::IniDialect::desktop());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!(vec!["A", "B\\;C", "", "D"], entry.get_list_items(';'));
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn locale_fallbacks() {
+    assert_eq!(vec!["de_AT@euro", "de_AT", "de@euro", "de"], super::locale_fallbacks("de_AT.UTF-8@euro"));
+    assert_eq!(vec!["de_AT", "de"],                           super::locale_fallbacks("de_AT"));
+    assert_eq!(vec!["sr@latin", "sr"],                        super::locale_fallbacks("sr@latin"));
+    assert_eq!(vec!["de"],                                    super::locale_fallbacks("de"));
+}
+
+
+#[test]
+fn parse_reg_quoted_key() {
+    let parsed = super::IniLine::parse(r#""Name = \"X\""="a \"b\" \\ c""#, "", &super::IniDialect::reg());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!(r#""Name = \"X\"""#, entry.key);
+            assert_eq!("=",                 entry.separator);
+            assert_eq!(r#"Name = "X""#,     entry.get_key());
+            assert_eq!(r#"a "b" \ c"#,      entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_reg_unfinished_quoted_key() {
+    let parsed = super::IniLine::parse(r#""Name=1"#, "", &super::IniDialect::reg());
+    match parsed.content {
+        super::IniContent::Other(other) => { assert_eq!(r#""Name=1"#, other.text); },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_reg_typed() {
+    let parsed = super::IniLine::parse("@=dword:0000001f", "", &super::IniDialect::reg());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("@",                entry.get_key());
+            assert_eq!("dword:0000001f",   entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_reg_continuation() {
+    let parsed = super::IniLine::parse("\"Bin\"=hex:01,02,\\\r\n  03,04", "\r\n", &super::IniDialect::reg());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("hex:01,02,\\\r\n  03,04", entry.value);
+            assert_eq!("hex:01,02,03,04",         entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+
+#[test]
+fn parse_mysql_include() {
+    let parsed = super::IniLine::parse("  !includedir   /etc/my.cnf.d ", "\n", &super::IniDialect::mysql());
+    match parsed.content {
+        super::IniContent::Directive(directive) => {
+            assert_eq!("  !includedir   /etc/my.cnf.d ", directive.to_string());
+            assert_eq!("!includedir",                     directive.name);
+            assert_eq!("/etc/my.cnf.d",                   directive.argument);
+            assert_eq!("!includedir /etc/my.cnf.d",       directive.reformatted().to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_mysql_include_not_in_ini() {
+    let parsed = super::IniLine::parse("!include /etc/my.cnf.d/a.cnf", "\n", &super::IniDialect::ini());
+    match parsed.content {
+        super::IniContent::Other(_) => {},
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_mysql_flag() {
+    let parsed = super::IniLine::parse("skip-networking  # no TCP", "\n", &super::IniDialect::mysql());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("skip-networking  # no TCP",   entry.to_string());
+            assert_eq!("skip-networking",             entry.key);
+            assert_eq!("",                            entry.separator);
+            assert_eq!("",                            entry.get_value_unquoted());
+            assert_eq!("skip-networking=1  # no TCP", entry.with_modified_value("1").to_string());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_mysql_flag_not_in_ini() {
+    let parsed = super::IniLine::parse("skip-networking", "\n", &super::IniDialect::ini());
+    match parsed.content {
+        super::IniContent::Other(_) => {},
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_mysql_inline_comment() {
+    let parsed = super::IniLine::parse("max_connections = 100  # more", "\n", &super::IniDialect::mysql());
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("max_connections", entry.key);
+            assert_eq!("100",             entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn compare_mysql() {
+    let compare = super::IniCompare::MySql;
+    assert!(compare.is_match("max-connections", "MAX_CONNECTIONS"));
+    assert!(compare.is_match("loose-innodb-foo", "innodb_foo"));
+    assert!(compare.is_match("loose_innodb_foo", "loose-innodb-foo"));
+    assert!(!compare.is_match("innodb-foo", "innodb-bar"));
+    assert_eq!("mysqld-8.0", compare.normalized_section("MySQLd-8.0"));
+}
+
+#[test]
+fn dialect_from_file_name() {
+    assert!(super::IniDialect::from_file_name("/app/.env")                                         == Some(super::IniDialect::dotenv()));
+    assert!(super::IniDialect::from_file_name(".env.local")                                        == Some(super::IniDialect::dotenv()));
+    assert!(super::IniDialect::from_file_name("/etc/php/8.1/cli/php.ini")                          == Some(super::IniDialect::php()));
+    assert!(super::IniDialect::from_file_name("php-development.ini")                               == Some(super::IniDialect::php()));
+    assert!(super::IniDialect::from_file_name("/etc/mysql/my.cnf")                                 == Some(super::IniDialect::mysql()));
+    assert!(super::IniDialect::from_file_name("/home/user/.gitconfig")                             == Some(super::IniDialect::gitconfig()));
+    assert!(super::IniDialect::from_file_name("repo/.git/config")                                  == Some(super::IniDialect::gitconfig()));
+    assert!(super::IniDialect::from_file_name("firefox.desktop")                                   == Some(super::IniDialect::desktop()));
+    assert!(super::IniDialect::from_file_name("Settings.REG")                                      == Some(super::IniDialect::reg()));
+    assert!(super::IniDialect::from_file_name("/etc/systemd/system/nginx.service")                 == Some(super::IniDialect::systemd()));
+    assert!(super::IniDialect::from_file_name("/etc/systemd/system/nginx.service.d/override.conf") == Some(super::IniDialect::systemd()));
+    assert!(super::IniDialect::from_file_name("/etc/nginx/nginx.conf")                             == None);
+    assert!(super::IniDialect::from_file_name("settings.ini")                                      == None);
+}
+
+#[test]
+fn dialect_valid_key() {
+    assert!(super::IniDialect::ini().is_valid_key("Name With Space"));
+    assert!(!super::IniDialect::ini().is_valid_key("a:b"));
+    assert!(!super::IniDialect::ini().is_valid_key("a=b"));
+    assert!(super::IniDialect::systemd().is_valid_key("a:b"));
+    assert!(super::IniDialect::reg().is_valid_key("a=b"));
+}
+
+#[test]
+fn parse_gitconfig() {
+    let dialect = super::IniDialect::gitconfig();
+    match super::IniLine::parse("\tpath = \"C:/Program Files\"/app ; comment", "", &dialect).content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!("path",                  entry.get_key());
+            assert_eq!("C:/Program Files/app",  entry.get_value_unquoted());
+        },
+        _ => panic!("failed match"),
+    }
+    match super::IniLine::parse("\tbare", "", &dialect).content {
+        super::IniContent::Entry(entry) => { assert_eq!("bare", entry.get_key()); },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn parse_gitconfig_escaped_backslash() {
+    let dialect = super::IniDialect::gitconfig();
+    assert!(!super::IniLine::parse("path = C:\\\\dir\\\\", "\n", &dialect).is_continued());
+    assert!(super::IniLine::parse("path = C:\\\\dir\\\\\\", "\n", &dialect).is_continued());
+    assert!(super::IniLine::parse("path = a \\", "\n", &dialect).is_continued());
+}
+
+#[test]
+fn filter_gitconfig_subsection_case() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[remote \"origin\"]", "\n", &super::IniDialect::gitconfig()));
+    lines.push(super::IniLine::parse("url = a",             "",   &super::IniDialect::gitconfig()));
+    let file = super::IniFile::new(lines, super::IniDialect::gitconfig());
+
+    assert!(file.contains(Some("remote.origin"), Some("URL")));
+    assert!(file.contains(Some("REMOTE.origin"), Some("url")));
+    assert!(!file.contains(Some("remote.ORIGIN"), Some("url"))); //subsection is case-sensitive
+}
+
+#[test]
+fn parse_php() {
+    let dialect = super::IniDialect::php();
+    match super::IniLine::parse("Memory_Limit = 128M ; default", "", &dialect).content {
+        super::IniContent::Entry(entry) => { assert_eq!("128M", entry.get_value_unquoted()); },
+        _ => panic!("failed match"),
+    }
+    assert!(matches!(super::IniLine::parse("# not comment", "", &dialect).content, super::IniContent::Other(_)));
+}
+
+#[test]
+fn filter_windows() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[Boot Loader]",  "\n"));
+    lines.push(super::IniLine::new("Time Out=30",    "\n"));
+    lines.push(super::IniLine::new("time out=10",    "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::windows());
+
+    file.filter(Some("boot loader"), Some("TIME OUT"));
+    let file = file;
+    print(&file);
+
+    assert!(super::IniDialect::windows().get_duplicates() == super::IniDuplicates::FirstWins);
+    assert_eq!(2,              file.lines.len());
+    assert_eq!("Time Out=30",  file.lines[0].content.to_string());
+}
+
+#[test]
+fn encoding_utf16le() {
+    let bytes = super::IniEncoding::Utf16Le.encode("[A]\r\nü=€\r\n").unwrap();
+    assert_eq!(vec![0xFF, 0xFE, b'[', 0, b'A', 0, b']', 0], bytes[..8].to_vec());
+    assert!(super::IniEncoding::detect(&bytes) == super::IniEncoding::Utf16Le);
+    assert_eq!("[A]\r\nü=€\r\n", super::IniEncoding::Utf16Le.decode(&bytes).unwrap());
+    assert!(super::IniEncoding::Utf16Le.decode(&bytes[..5]).is_err());
+    assert!(super::IniEncoding::detect(b"[A]") == super::IniEncoding::Utf8);
+}
+
+#[test]
+fn encoding_detect() {
+    assert!(super::IniEncoding::detect(b"\xEF\xBB\xBF[A]") == super::IniEncoding::Utf8Bom);
+    assert!(super::IniEncoding::detect(b"\xFF\xFE[\0")      == super::IniEncoding::Utf16Le);
+    assert!(super::IniEncoding::detect(b"\xFE\xFF\0[")      == super::IniEncoding::Utf16Be);
+    assert!(super::IniEncoding::detect(b"[A]")              == super::IniEncoding::Utf8);
+    assert!(super::IniEncoding::detect(b"")                 == super::IniEncoding::Utf8);
+}
+
+#[test]
+fn encoding_utf8_bom() {
+    let bytes = super::IniEncoding::Utf8Bom.encode("[A]").unwrap();
+    assert_eq!(b"\xEF\xBB\xBF[A]".to_vec(), bytes);
+    assert_eq!("[A]", super::IniEncoding::Utf8Bom.decode(&bytes).unwrap());
+    assert_eq!("[A]", super::IniEncoding::Utf8.decode(&bytes).unwrap());
+    assert_eq!(b"[A]".to_vec(), super::IniEncoding::Utf8.encode("[A]").unwrap());
+}
+
+#[test]
+fn encoding_utf16be() {
+    let bytes = super::IniEncoding::Utf16Be.encode("[A]\r\nü=€\r\n").unwrap();
+    assert_eq!(vec![0xFE, 0xFF, 0, b'[', 0, b'A', 0, b']'], bytes[..8].to_vec());
+    assert_eq!("[A]\r\nü=€\r\n", super::IniEncoding::Utf16Be.decode(&bytes).unwrap());
+    assert_eq!("[A]", super::IniEncoding::Utf16Be.decode(&[0, b'[', 0, b'A', 0, b']']).unwrap()); //byte order mark is optional
+}
+
+#[test]
+fn encoding_utf16_invalid_bytes() {
+    let text = super::from_bytes_lossless(b"x=\xFF");
+    assert!(super::IniEncoding::Utf16Le.encode(&text).is_err());
+    assert!(super::IniEncoding::Utf16Be.encode(&text).is_err());
+    assert_eq!(b"x=\xFF".to_vec(), super::IniEncoding::Utf8.encode(&text).unwrap());
+}
+
+#[test]
+fn lossless_invalid_bytes() {
+    let bytes = b"K\xE4se=\xFC\xE9 \xE2\x82 \xE2\x82\xAC";
+    let text = super::from_bytes_lossless(bytes);
+    assert_eq!(12, text.chars().count());
+    assert!(text.starts_with('K') && text.ends_with('€'));
+    assert_eq!(bytes.to_vec(), super::to_bytes_lossless(&text));
+}
+
+#[test]
+fn lossless_private_use_characters() {
+    let bytes = "x=\u{10FF41}\u{10FFFF}".as_bytes();
+    let text = super::from_bytes_lossless(bytes);
+    assert_eq!(10, text.chars().count()); //each byte is escaped
+    assert_eq!(bytes.to_vec(), super::to_bytes_lossless(&text));
+}
+
+#[test]
+fn parse_entry_invalid_bytes() {
+    let key = super::from_bytes_lossless(b"K\xE4se");
+    let parsed = super::IniLine::new(&super::from_bytes_lossless(b"K\xE4se = \xFC\xE9"), "");
+    match parsed.content {
+        super::IniContent::Entry(entry) => {
+            assert_eq!(key, entry.get_key());
+            assert_eq!(b"\xFC\xE9".to_vec(), super::to_bytes_lossless(&entry.get_value_unquoted()));
+        },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn encoding_utf16_errors() {
+    match super::IniEncoding::Utf16Le.decode(b"\xFF\xFE[\0\n\0a\0\0\xD8") {
+        Err(error) => { assert_eq!("2:2: unpaired UTF-16 surrogate", error.to_string()); },
+        _ => panic!("failed match"),
+    }
+    match super::IniEncoding::Utf16Be.decode(b"\0[\0]\0") {
+        Err(error) => { assert_eq!("1:3: incomplete UTF-16 character", error.to_string()); },
+        _ => panic!("failed match"),
+    }
+    match super::IniEncoding::Utf16Le.encode(&super::from_bytes_lossless(b"[A]\nx=\xFF")) {
+        Err(error) => { assert_eq!("test.ini:2:3: invalid UTF-8 byte cannot be converted to UTF-16", error.with_file_name(Some("test.ini")).to_string()); },
+        _ => panic!("failed match"),
+    }
+}
+
+#[test]
+fn syntax_errors() {
+    let dialect = super::IniDialect::ini();
+    for (text, column, message) in &[("  [mysqld", 3, "section is not closed"), ("key_buffer 1M", 1, "line is not a section, an entry, or a comment")] {
+        match super::IniLine::new(text, "").get_syntax_error(&dialect, false) {
+            Some(error) => { assert_eq!((*column, *message), error); },
+            None => panic!("failed match"),
+        }
+    }
+    for text in &["", "  ", "[A]", "a=1", "; [B"] {
+        assert!(super::IniLine::new(text, "").get_syntax_error(&dialect, false).is_none());
+    }
+}
+
+#[test]
+fn syntax_errors_header() {
+    let line = super::IniLine::parse("Windows Registry Editor Version 5.00", "", &super::IniDialect::reg());
+    assert!(line.get_syntax_error(&super::IniDialect::reg(), true).is_none());
+    assert!(line.get_syntax_error(&super::IniDialect::reg(), false).is_some());
+    assert!(line.get_syntax_error(&super::IniDialect::ini(), true).is_some());
+}
+
+
+#[test]
+fn pretty_print_adds_empty_line() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),  line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),  line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.reformat();
+    let file = file;
+    print(&file);
+
+    assert_eq!("[X]", file.lines[0].content.to_string());
+    assert_eq!("A=1", file.lines[1].content.to_string());
+    assert_eq!("",    file.lines[2].content.to_string());
+    assert_eq!("[Y]", file.lines[3].content.to_string());
+    assert_eq!("B=2", file.lines[4].content.to_string());
+}
+
+#[test]
+fn pretty_print_removes_empty_line() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),  line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),  line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry(super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.reformat();
+    let file = file;
+    print(&file);
+
+    assert_eq!("[X]", file.lines[0].content.to_string());
+    assert_eq!("A=1", file.lines[1].content.to_string());
+    assert_eq!("",    file.lines[2].content.to_string());
+    assert_eq!("[Y]", file.lines[3].content.to_string());
+    assert_eq!("B=2", file.lines[4].content.to_string());
+}
+
+#[test]
+fn filter_section() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.filter(Some("X"), None);
+    let file = file;
+    print(&file);
+
+    assert_eq!(2,      file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=1",  file.lines[1].content.to_string());
+}
+
+#[test]
+fn filter_entry_found() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.filter(Some("X"), Some("A"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(1,      file.lines.len());
+    assert_eq!("A=1",  file.lines[0].content.to_string());
+}
+
+#[test]
+fn filter_entry_section_not_found() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.filter(Some("-"), Some("A"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(0,      file.lines.len());
+}
+
+#[test]
+fn filter_entry_key_not_found() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.filter(Some("X"), Some("-"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(0,      file.lines.len());
+}
+
+#[test]
+fn filter_with_docs() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("; about X",    "\n"));
+    lines.push(super::IniLine::new("[X]",          "\n"));
+    lines.push(super::IniLine::new("; about A",    "\n"));
+    lines.push(super::IniLine::new("A=1",          "\n"));
+    lines.push(super::IniLine::new("; unrelated",  "\n"));
+    lines.push(super::IniLine::new("",             "\n"));
+    lines.push(super::IniLine::new("; about B",    "\n"));
+    lines.push(super::IniLine::new("; more",       "\n"));
+    lines.push(super::IniLine::new("B=2",          "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.filter_with_docs(Some("X"), Some("B"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,            file.lines.len());
+    assert_eq!("; about B",  file.lines[0].content.to_string());
+    assert_eq!("; more",     file.lines[1].content.to_string());
+    assert_eq!("B=2",        file.lines[2].content.to_string());
+}
+
+#[test]
+fn delete_with_docs() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]",        "\n"));
+    lines.push(super::IniLine::new("; about A",  "\n"));
+    lines.push(super::IniLine::new("A=1",        "\n"));
+    lines.push(super::IniLine::new("; about Y",  "\n"));
+    lines.push(super::IniLine::new("[Y]",        "\n"));
+    lines.push(super::IniLine::new("B=2",        "\n"));
+    lines.push(super::IniLine::new("; about Z",  "\n"));
+    lines.push(super::IniLine::new("[Z]",        "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.delete(Some("X"), Some("A"));
+    file.delete(Some("Y"), None);
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,            file.lines.len());
+    assert_eq!("[X]",        file.lines[0].content.to_string());
+    assert_eq!("; about Z",  file.lines[1].content.to_string());
+    assert_eq!("[Z]",        file.lines[2].content.to_string());
+}
+
+#[test]
+fn copy_entry_with_docs() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]",        "\n"));
+    lines.push(super::IniLine::new("; about A",  "\n"));
+    lines.push(super::IniLine::new("A=1",        "\n"));
+    lines.push(super::IniLine::new("[Y]",        "\n"));
+    lines.push(super::IniLine::new("; old A",    "\n"));
+    lines.push(super::IniLine::new("A=2",        "\n"));
+    lines.push(super::IniLine::new("B=3",        ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.copy_entry("X", "A", "Y");
+    let file = file;
+    print(&file);
+
+    assert_eq!(7,            file.lines.len());
+    assert_eq!("A=1",        file.lines[2].content.to_string());
+    assert_eq!("[Y]",        file.lines[3].content.to_string());
+    assert_eq!("B=3",        file.lines[4].content.to_string());
+    assert_eq!("\n",         file.lines[4].line_ending);
+    assert_eq!("; about A",  file.lines[5].content.to_string());
+    assert_eq!("A=1",        file.lines[6].content.to_string());
+    assert_eq!("",           file.lines[6].line_ending);
+}
+
+#[test]
+fn move_entry_with_docs() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]",        "\n"));
+    lines.push(super::IniLine::new("; about A",  "\n"));
+    lines.push(super::IniLine::new("A=1",        "\n"));
+    lines.push(super::IniLine::new("B=2",        "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.move_entry("X", "A", "X"); //nothing to move
+    file.move_entry("X", "A", "Y");
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,            file.lines.len());
+    assert_eq!("[X]",        file.lines[0].content.to_string());
+    assert_eq!("B=2",        file.lines[1].content.to_string());
+    assert_eq!("[Y]",        file.lines[2].content.to_string());
+    assert_eq!("; about A",  file.lines[3].content.to_string());
+    assert_eq!("A=1",        file.lines[4].content.to_string());
+}
+
+#[test]
+fn append_position() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]",        "\n"));
+    lines.push(super::IniLine::new("",           "\n"));
+    lines.push(super::IniLine::new("; about A",  "\n"));
+    lines.push(super::IniLine::new("A=1",        "\n"));
+    lines.push(super::IniLine::new("B=2",        "\n"));
+    lines.push(super::IniLine::new("B=3",        "\n"));
+    lines.push(super::IniLine::new("",           "\n"));
+    lines.push(super::IniLine::new("[Y]",        ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.set_edit_options(super::IniEditOptions { position: super::IniPosition::First, ..Default::default() });
+    file.edit("X", "C", "4", false, true);
+    file.set_edit_options(super::IniEditOptions { position: super::IniPosition::Before("A".to_string()), ..Default::default() });
+    file.edit("X", "D", "5", false, true);
+    file.set_edit_options(super::IniEditOptions { position: super::IniPosition::After("B".to_string()), ..Default::default() });
+    file.edit("X", "E", "6", false, true);
+    file.edit("Y", "F", "7", false, true); //key not found
+    let file = file;
+    print(&file);
+
+    assert_eq!(12,           file.lines.len());
+    assert_eq!("",           file.lines[1].content.to_string());
+    assert_eq!("C=4",        file.lines[2].content.to_string());
+    assert_eq!("D=5",        file.lines[3].content.to_string());
+    assert_eq!("; about A",  file.lines[4].content.to_string());
+    assert_eq!("A=1",        file.lines[5].content.to_string());
+    assert_eq!("B=3",        file.lines[7].content.to_string());
+    assert_eq!("E=6",        file.lines[8].content.to_string());
+    assert_eq!("",           file.lines[9].content.to_string());
+    assert_eq!("[Y]",        file.lines[10].content.to_string());
+    assert_eq!("F=7",        file.lines[11].content.to_string());
+    assert_eq!("",           file.lines[11].line_ending);
+}
+
+#[test]
+fn edit_position_existing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]",  "\n"));
+    lines.push(super::IniLine::new("A=1",  "\n"));
+    lines.push(super::IniLine::new("B=2",  "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.set_edit_options(super::IniEditOptions { position: super::IniPosition::First, ..Default::default() });
+    file.edit("X", "B", "3", true, true); //existing entry stays where it is
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,      file.lines.len());
+    assert_eq!("A=1",  file.lines[1].content.to_string());
+    assert_eq!("B=3",  file.lines[2].content.to_string());
+}
+
+#[test]
+fn append_section_position() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[B]",        "\n"));
+    lines.push(super::IniLine::new("x=1",        "\n"));
+    lines.push(super::IniLine::new("",           "\n"));
+    lines.push(super::IniLine::new("; about D",  "\n"));
+    lines.push(super::IniLine::new("[D]",        "\n"));
+    lines.push(super::IniLine::new("y=2",        ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.set_edit_options(super::IniEditOptions { section_position: super::IniSectionPosition::Sorted, ..Default::default() });
+    file.edit("C", "n", "3", false, true);
+    file.set_edit_options(super::IniEditOptions { section_position: super::IniSectionPosition::Before("B".to_string()), ..Default::default() });
+    file.edit("A", "n", "4", false, true);
+    file.set_edit_options(super::IniEditOptions { section_position: super::IniSectionPosition::After("D".to_string()), ..Default::default() });
+    file.edit("E", "n", "5", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(15,           file.lines.len());
+    assert_eq!("[A]",        file.lines[0].content.to_string());
+    assert_eq!("n=4",        file.lines[1].content.to_string());
+    assert_eq!("",           file.lines[2].content.to_string());
+    assert_eq!("[B]",        file.lines[3].content.to_string());
+    assert_eq!("",           file.lines[5].content.to_string());
+    assert_eq!("[C]",        file.lines[6].content.to_string());
+    assert_eq!("n=3",        file.lines[7].content.to_string());
+    assert_eq!("",           file.lines[8].content.to_string());
+    assert_eq!("; about D",  file.lines[9].content.to_string());
+    assert_eq!("[D]",        file.lines[10].content.to_string());
+    assert_eq!("y=2",        file.lines[11].content.to_string());
+    assert_eq!("\n",         file.lines[11].line_ending);
+    assert_eq!("",           file.lines[12].content.to_string());
+    assert_eq!("[E]",        file.lines[13].content.to_string());
+    assert_eq!("n=5",        file.lines[14].content.to_string());
+    assert_eq!("",           file.lines[14].line_ending);
+}
+
+#[test]
+fn append_section_no_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[B]",  "\n"));
+    lines.push(super::IniLine::new("x=1",  "\n"));
+    lines.push(super::IniLine::new("",     "\n"));
+    lines.push(super::IniLine::new("[D]",  "\n"));
+    lines.push(super::IniLine::new("y=2",  "\n"));
+    lines.push(super::IniLine::new("[F]",  "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.set_edit_options(super::IniEditOptions { section_position: super::IniSectionPosition::Sorted, ..Default::default() });
+    file.edit("E", "n", "3", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(8,      file.lines.len());
+    assert_eq!("y=2",  file.lines[4].content.to_string());
+    assert_eq!("[E]",  file.lines[5].content.to_string());
+    assert_eq!("n=3",  file.lines[6].content.to_string());
+    assert_eq!("[F]",  file.lines[7].content.to_string());
+}
+
+#[test]
+fn sort_natural() {
+    assert!(super::natural_cmp("key2", "key10")  == std::cmp::Ordering::Less);
+    assert!(super::natural_cmp("Key2", "key02")  == std::cmp::Ordering::Less);
+    assert!(super::natural_cmp("B", "a")         == std::cmp::Ordering::Greater);
+    assert!(super::natural_cmp("a", "A")         == std::cmp::Ordering::Greater);
+    assert!(super::natural_cmp("a", "a1")        == std::cmp::Ordering::Less);
+    assert!(super::natural_cmp("x", "x")         == std::cmp::Ordering::Equal);
+}
+
+#[test]
+fn sort_sections() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("g=0",        "\n"));
+    lines.push(super::IniLine::new("[b10]",      "\n"));
+    lines.push(super::IniLine::new("x=1",        "\n"));
+    lines.push(super::IniLine::new("",           "\n"));
+    lines.push(super::IniLine::new("; about B2", "\n"));
+    lines.push(super::IniLine::new("[B2]",       "\n"));
+    lines.push(super::IniLine::new("y=2",        ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.sort_sections();
+    let file = file;
+    print(&file);
+
+    assert_eq!(7,             file.lines.len());
+    assert_eq!("g=0",         file.lines[0].content.to_string());
+    assert_eq!("; about B2",  file.lines[1].content.to_string());
+    assert_eq!("[B2]",        file.lines[2].content.to_string());
+    assert_eq!("y=2",         file.lines[3].content.to_string());
+    assert_eq!("\n",          file.lines[3].line_ending);
+    assert_eq!("",            file.lines[4].content.to_string());
+    assert_eq!("[b10]",       file.lines[5].content.to_string());
+    assert_eq!("x=1",         file.lines[6].content.to_string());
+    assert_eq!("",            file.lines[6].line_ending);
+}
+
+#[test]
+fn sort_keys() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]",        "\n"));
+    lines.push(super::IniLine::new("k10=1",      "\n"));
+    lines.push(super::IniLine::new("; about k2", "\n"));
+    lines.push(super::IniLine::new("k2=2",       "\n"));
+    lines.push(super::IniLine::new("",           "\n"));
+    lines.push(super::IniLine::new("K1=3",       "\n"));
+    lines.push(super::IniLine::new("[A]",        "\n"));
+    lines.push(super::IniLine::new("b=4",        "\n"));
+    lines.push(super::IniLine::new("a=5",        "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.sort_keys();
+    let file = file;
+    print(&file);
+
+    assert_eq!(9,             file.lines.len());
+    assert_eq!("[X]",         file.lines[0].content.to_string());
+    assert_eq!("K1=3",        file.lines[1].content.to_string());
+    assert_eq!("; about k2",  file.lines[2].content.to_string());
+    assert_eq!("k2=2",        file.lines[3].content.to_string());
+    assert_eq!("",            file.lines[4].content.to_string());
+    assert_eq!("k10=1",       file.lines[5].content.to_string());
+    assert_eq!("[A]",         file.lines[6].content.to_string());
+    assert_eq!("a=5",         file.lines[7].content.to_string());
+    assert_eq!("b=4",         file.lines[8].content.to_string());
+}
+
+#[test]
+fn append_no_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", false, true);
+    file.edit("Y", "B", "20", false, true);
+    file.edit("X", "C", "30", false, true);
+    file.edit("Y", "D", "40", false, true);
+    file.edit("Z", "E", "50", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(10,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=1",  file.lines[1].content.to_string());
+    assert_eq!("A=10", file.lines[2].content.to_string());
+    assert_eq!("C=30", file.lines[3].content.to_string());
+    assert_eq!("[Y]",  file.lines[4].content.to_string());
+    assert_eq!("B=2",  file.lines[5].content.to_string());
+    assert_eq!("B=20", file.lines[6].content.to_string());
+    assert_eq!("D=40", file.lines[7].content.to_string());
+    assert_eq!("[Z]",  file.lines[8].content.to_string());
+    assert_eq!("E=50", file.lines[9].content.to_string());
+}
+
+#[test]
+fn append_no_final_line_ending() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]", "\r\n"));
+    lines.push(super::IniLine::new("A=1", ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "B", "2", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,      file.lines.len());
+    assert_eq!("A=1",  file.lines[1].content.to_string());
+    assert_eq!("\r\n", file.lines[1].line_ending);
+    assert_eq!("B=2",  file.lines[2].content.to_string());
+    assert_eq!("",     file.lines[2].line_ending);
+}
+
+#[test]
+fn append_single_line_no_final_line_ending() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]", ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("Y", "B", "2", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,     file.lines.len());
+    assert_eq!("[X]", file.lines[0].content.to_string());
+    assert_eq!("\n",  file.lines[0].line_ending);
+    assert_eq!("[Y]", file.lines[1].content.to_string());
+    assert_eq!("\n",  file.lines[1].line_ending);
+    assert_eq!("B=2", file.lines[2].content.to_string());
+    assert_eq!("",    file.lines[2].line_ending);
+}
+
+#[test]
+fn append_single_entry_no_final_line_ending() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("A=1", ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("", "B", "2", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(2,     file.lines.len());
+    assert_eq!("A=1", file.lines[0].content.to_string());
+    assert_eq!("\n",  file.lines[0].line_ending);
+    assert_eq!("B=2", file.lines[1].content.to_string());
+    assert_eq!("",    file.lines[1].line_ending);
+}
+
+#[test]
+fn append_section_no_final_line_ending() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]", "\n"));
+    lines.push(super::IniLine::new("A=1", ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("Y", "B", "2", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(4,     file.lines.len());
+    assert_eq!("\n",  file.lines[1].line_ending);
+    assert_eq!("[Y]", file.lines[2].content.to_string());
+    assert_eq!("\n",  file.lines[2].line_ending);
+    assert_eq!("B=2", file.lines[3].content.to_string());
+    assert_eq!("",    file.lines[3].line_ending);
+}
+
+#[test]
+fn append_no_final_line_ending_after_other() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[X]", "\n"));
+    lines.push(super::IniLine::new("A=1", "\n"));
+    lines.push(super::IniLine::new("",    "\n"));
+    lines.push(super::IniLine::new("[Y]", ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "B", "2", false, true);
+    file.edit("Y", "C", "3", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(6,     file.lines.len());
+    assert_eq!("B=2", file.lines[2].content.to_string());
+    assert_eq!("\n",  file.lines[2].line_ending);
+    assert_eq!("[Y]", file.lines[4].content.to_string());
+    assert_eq!("\n",  file.lines[4].line_ending);
+    assert_eq!("C=3", file.lines[5].content.to_string());
+    assert_eq!("",    file.lines[5].line_ending);
+}
+
+#[test]
+fn append_with_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", false, true);
+    file.edit("Y", "B", "20", false, true);
+    file.edit("X", "C", "30", false, true);
+    file.edit("Y", "D", "40", false, true);
+    file.edit("Z", "E", "50", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(13,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=1",  file.lines[1].content.to_string());
+    assert_eq!("#-",   file.lines[2].content.to_string());
+    assert_eq!("A=10", file.lines[3].content.to_string());
+    assert_eq!("C=30", file.lines[4].content.to_string());
+    assert_eq!("",     file.lines[5].content.to_string());
+    assert_eq!("[Y]",  file.lines[6].content.to_string());
+    assert_eq!("B=2",  file.lines[7].content.to_string());
+    assert_eq!("B=20", file.lines[8].content.to_string());
+    assert_eq!("D=40", file.lines[9].content.to_string());
+    assert_eq!("",     file.lines[10].content.to_string());
+    assert_eq!("[Z]",  file.lines[11].content.to_string());
+    assert_eq!("E=50", file.lines[12].content.to_string());
+}
+
+#[test]
+fn append_with_multi_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", false, true);
+    file.edit("Y", "B", "20", false, true);
+    file.edit("X", "C", "30", false, true);
+    file.edit("Y", "D", "40", false, true);
+    file.edit("Z", "E", "50", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(16,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=1",  file.lines[1].content.to_string());
+    assert_eq!("#-",   file.lines[2].content.to_string());
+    assert_eq!("A=10", file.lines[3].content.to_string());
+    assert_eq!("C=30", file.lines[4].content.to_string());
+    assert_eq!("",     file.lines[5].content.to_string());
+    assert_eq!("",     file.lines[6].content.to_string());
+    assert_eq!("",     file.lines[7].content.to_string());
+    assert_eq!("[Y]",  file.lines[8].content.to_string());
+    assert_eq!("B=2",  file.lines[9].content.to_string());
+    assert_eq!("B=20", file.lines[10].content.to_string());
+    assert_eq!("D=40", file.lines[11].content.to_string());
+    assert_eq!("",     file.lines[12].content.to_string());
+    assert_eq!("",     file.lines[13].content.to_string());
+    assert_eq!("[Z]",  file.lines[14].content.to_string());
+    assert_eq!("E=50", file.lines[15].content.to_string());
+}
+
+#[test]
+fn change_no_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", true, false);
+    file.edit("Y", "B", "20", true, false);
+    file.edit("X", "C", "30", true, false);
+    file.edit("Y", "D", "40", true, false);
+    file.edit("Z", "E", "50", true, false);
+    let file = file;
+    print(&file);
+
+    assert_eq!(4,      file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=10", file.lines[1].content.to_string());
+    assert_eq!("[Y]",  file.lines[2].content.to_string());
+    assert_eq!("B=20", file.lines[3].content.to_string());
+}
+
+#[test]
+fn change_with_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", true, false);
+    file.edit("Y", "B", "20", true, false);
+    file.edit("X", "C", "30", true, false);
+    file.edit("Y", "D", "40", true, false);
+    file.edit("Z", "E", "50", true, false);
+    let file = file;
+    print(&file);
+
+    assert_eq!(7,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=10", file.lines[1].content.to_string());
+    assert_eq!("#-",   file.lines[2].content.to_string());
+    assert_eq!("",     file.lines[3].content.to_string());
+    assert_eq!("[Y]",  file.lines[4].content.to_string());
+    assert_eq!("B=20", file.lines[5].content.to_string());
+    assert_eq!("",     file.lines[6].content.to_string());
+}
+
+#[test]
+fn change_with_multi_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", true, false);
+    file.edit("Y", "B", "20", true, false);
+    file.edit("X", "C", "30", true, false);
+    file.edit("Y", "D", "40", true, false);
+    file.edit("Z", "E", "50", true, false);
+    let file = file;
+    print(&file);
+
+    assert_eq!(10,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=10", file.lines[1].content.to_string());
+    assert_eq!("#-",   file.lines[2].content.to_string());
+    assert_eq!("",     file.lines[3].content.to_string());
+    assert_eq!("",     file.lines[4].content.to_string());
+    assert_eq!("",     file.lines[5].content.to_string());
+    assert_eq!("[Y]",  file.lines[6].content.to_string());
+    assert_eq!("B=20", file.lines[7].content.to_string());
+    assert_eq!("",     file.lines[8].content.to_string());
+    assert_eq!("",     file.lines[9].content.to_string());
+}
+
+#[test]
+fn edit_no_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
     lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
 
-    file.edit("X", "A", "10", false, true);
-    file.edit("Y", "B", "20", false, true);
-    file.edit("X", "C", "30", false, true);
-    file.edit("Y", "D", "40", false, true);
-    file.edit("Z", "E", "50", false, true);
+    file.edit("X", "A", "10", true, true);
+    file.edit("Y", "B", "20", true, true);
+    file.edit("X", "C", "30", true, true);
+    file.edit("Y", "D", "40", true, true);
+    file.edit("Z", "E", "50", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(8,      file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=10", file.lines[1].content.to_string());
+    assert_eq!("C=30", file.lines[2].content.to_string());
+    assert_eq!("[Y]",  file.lines[3].content.to_string());
+    assert_eq!("B=20", file.lines[4].content.to_string());
+    assert_eq!("D=40", file.lines[5].content.to_string());
+    assert_eq!("[Z]",  file.lines[6].content.to_string());
+    assert_eq!("E=50", file.lines[7].content.to_string());
+}
+
+#[test]
+fn edit_with_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", true, true);
+    file.edit("Y", "B", "20", true, true);
+    file.edit("X", "C", "30", true, true);
+    file.edit("Y", "D", "40", true, true);
+    file.edit("Z", "E", "50", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(11,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=10", file.lines[1].content.to_string());
+    assert_eq!("#-",   file.lines[2].content.to_string());
+    assert_eq!("C=30", file.lines[3].content.to_string());
+    assert_eq!("",     file.lines[4].content.to_string());
+    assert_eq!("[Y]",  file.lines[5].content.to_string());
+    assert_eq!("B=20", file.lines[6].content.to_string());
+    assert_eq!("D=40", file.lines[7].content.to_string());
+    assert_eq!("",     file.lines[8].content.to_string());
+    assert_eq!("[Z]",  file.lines[9].content.to_string());
+    assert_eq!("E=50", file.lines[10].content.to_string());
+}
+
+#[test]
+fn edit_with_multi_spacing() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("X", "A", "10", true, true);
+    file.edit("Y", "B", "20", true, true);
+    file.edit("X", "C", "30", true, true);
+    file.edit("Y", "D", "40", true, true);
+    file.edit("Z", "E", "50", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(14,     file.lines.len());
+    assert_eq!("[X]",  file.lines[0].content.to_string());
+    assert_eq!("A=10", file.lines[1].content.to_string());
+    assert_eq!("#-",   file.lines[2].content.to_string());
+    assert_eq!("C=30", file.lines[3].content.to_string());
+    assert_eq!("",     file.lines[4].content.to_string());
+    assert_eq!("",     file.lines[5].content.to_string());
+    assert_eq!("",     file.lines[6].content.to_string());
+    assert_eq!("[Y]",  file.lines[7].content.to_string());
+    assert_eq!("B=20", file.lines[8].content.to_string());
+    assert_eq!("D=40", file.lines[9].content.to_string());
+    assert_eq!("",     file.lines[10].content.to_string());
+    assert_eq!("",     file.lines[11].content.to_string());
+    assert_eq!("[Z]",  file.lines[12].content.to_string());
+    assert_eq!("E=50", file.lines[13].content.to_string());
+}
+
+#[test]
+fn edit_without_section() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "2")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("", "A", "10", true, true);
+    file.edit("", "B", "20", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,      file.lines.len());
+    assert_eq!("A=10", file.lines[0].content.to_string());
+    assert_eq!("B=20", file.lines[1].content.to_string());
+    assert_eq!("",     file.lines[2].content.to_string());
+    assert_eq!("[X]",  file.lines[3].content.to_string());
+    assert_eq!("A=2",  file.lines[4].content.to_string());
+}
+
+#[test]
+fn edit_without_section_before_first_section() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("", "A", "10", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,      file.lines.len());
+    assert_eq!("A=10", file.lines[0].content.to_string());
+    assert_eq!("[X]",  file.lines[1].content.to_string());
+    assert_eq!("A=1",  file.lines[2].content.to_string());
+}
+
+#[test]
+fn edit_flag() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[mysqld]",        "\n", &super::IniDialect::mysql()));
+    lines.push(super::IniLine::parse("skip-networking", "\n", &super::IniDialect::mysql()));
+    lines.push(super::IniLine::parse("skip-bdb",        "\n", &super::IniDialect::mysql()));
+    lines.push(super::IniLine::parse("",                "\n", &super::IniDialect::mysql()));
+    lines.push(super::IniLine::parse("[client]",        "\n", &super::IniDialect::mysql()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::mysql());
+
+    assert!(file.contains(Some("mysqld"), Some("skip-bdb")));
+    assert!(!file.contains(Some("client"), Some("skip-bdb")));
+    assert!(file.contains(Some("client"), None));
+
+    file.edit("mysqld", "skip-networking", "1", true, true);
+    file.delete(Some("mysqld"), Some("skip-bdb"));
+    file.add_flag("mysqld", "skip-grant-tables");
+    file.add_flag("mysqld", "skip-grant-tables");
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,                   file.lines.len());
+    assert_eq!("[mysqld]",          file.lines[0].content.to_string());
+    assert_eq!("skip-networking=1", file.lines[1].content.to_string());
+    assert_eq!("skip-grant-tables", file.lines[2].content.to_string());
+    assert_eq!("",                  file.lines[3].content.to_string());
+    assert_eq!("[client]",          file.lines[4].content.to_string());
+}
+
+#[test]
+fn edit_key_with_spaces() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[Section]",           "\n"));
+    lines.push(super::IniLine::new("Name With Space = 1", "\n"));
+    lines.push(super::IniLine::new("Name = 2",            "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("Section", "Name With Space", "10", true, true);
+    file.delete(Some("Section"), Some("Name"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(2,                      file.lines.len());
+    assert_eq!("Name With Space = 10", file.lines[1].content.to_string());
+}
+
+#[test]
+fn encoded_value_round_trip() {
+    let values = ["", "1", "a ; b", "a # b", " lead", "trail ", "\"quoted\"", "'single'", "it's", "C:\\Windows\\new", "end\\", "two\nlines", "tab\tinside", "\\n", "\\\\", "new val", "$HOME", "a$b'c", "`date`"];
+    for dialect in &[super::IniDialect::ini(), super::IniDialect::dotenv(), super::IniDialect::desktop(), super::IniDialect::mysql(), super::IniDialect::gitconfig()] {
+        for quote_char in &['"', '\''] {
+            for value in &values {
+                let encoded = dialect.encoded_value(value, *quote_char, false);
+                match super::IniLine::parse(&format!("Key={}", encoded), "", dialect).content {
+                    super::IniContent::Entry(entry) => { assert_eq!(*value, entry.get_value_unquoted(), "encoded as {}", encoded); },
+                    _ => panic!("failed match"),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn encoded_value_only_when_needed() {
+    let dialect = super::IniDialect::ini();
+    assert_eq!("Value With Spaces",  dialect.encoded_value("Value With Spaces", '"', false));
+    assert_eq!("C:\\Windows",      dialect.encoded_value("C:\\Windows", '"', false));
+    assert_eq!("\"a ; b\"",        dialect.encoded_value("a ; b", '"', false));
+    assert_eq!("'a'",                dialect.encoded_value("a", '\'', true));
+}
+
+#[test]
+fn edit_quoting() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[A]",     "\n"));
+    lines.push(super::IniLine::new("x='1'",   "\n"));
+    lines.push(super::IniLine::new("y=2",     "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("A", "x", "10", true, true);
+    file.edit("A", "y", "a ; b", true, true);
+    file.edit("A", "z", "30", true, true);
+    file.set_edit_options(super::IniEditOptions { raw: true, ..Default::default() });
+    file.edit("A", "w", "a ; b", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,           file.lines.len());
+    assert_eq!("x='10'",    file.lines[1].content.to_string());
+    assert_eq!("y='a ; b'", file.lines[2].content.to_string());
+    assert_eq!("z=30",      file.lines[3].content.to_string());
+    assert_eq!("w=a ; b",   file.lines[4].content.to_string());
+}
+
+#[test]
+fn edit_quoting_section() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[A]",     "\n"));
+    lines.push(super::IniLine::new("x='1'",   "\n"));
+    lines.push(super::IniLine::new("[B]",     "\n"));
+    lines.push(super::IniLine::new("y=2",     "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("A", "z", "a ; b", true, true);
+    file.edit("B", "z", "a ; b", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(6,             file.lines.len());
+    assert_eq!("z='a ; b'",   file.lines[2].content.to_string());
+    assert_eq!("z=\"a ; b\"", file.lines[5].content.to_string());
+}
+
+#[test]
+fn edit_comment() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[A]",          "\n"));
+    lines.push(super::IniLine::new("x=1",          "\n"));
+    lines.push(super::IniLine::new("; old",        "\n"));
+    lines.push(super::IniLine::new("y=2",          "\n"));
+    lines.push(super::IniLine::new("",             "\n"));
+    lines.push(super::IniLine::new("[B]",          "\n"));
+    lines.push(super::IniLine::new("z=3",          ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.set_edit_options(super::IniEditOptions { comment: Some("managed".to_string()), ..Default::default() });
+    file.edit("A", "x", "10", true, true);
+    file.edit("A", "y", "20", true, true);
+    file.edit("A", "w", "40", true, true);
+    file.edit("A", "w", "40", true, true); //no duplicate comment
+    file.edit("B", "z", "30", true, true);
+    file.edit("A", "y", "21", true, true); //no duplicate comment for existing entry
+    file.set_edit_options(super::IniEditOptions { comment: Some("managed again".to_string()), ..Default::default() });
+    file.edit("A", "x", "11", true, true); //comment written earlier is updated
+    let file = file;
+    print(&file);
+
+    assert_eq!(12,                       file.lines.len());
+    assert_eq!("[A]",                    file.lines[0].content.to_string());
+    assert_eq!("; inied: managed again", file.lines[1].content.to_string());
+    assert_eq!("x=11",                   file.lines[2].content.to_string());
+    assert_eq!("; old",                  file.lines[3].content.to_string());
+    assert_eq!("; inied: managed",       file.lines[4].content.to_string());
+    assert_eq!("y=21",                   file.lines[5].content.to_string());
+    assert_eq!("; inied: managed",       file.lines[6].content.to_string());
+    assert_eq!("w=40",                   file.lines[7].content.to_string());
+    assert_eq!("",                       file.lines[8].content.to_string());
+    assert_eq!("[B]",                    file.lines[9].content.to_string());
+    assert_eq!("; inied: managed",       file.lines[10].content.to_string());
+    assert_eq!("\n",                     file.lines[10].line_ending);
+    assert_eq!("z=30",                   file.lines[11].content.to_string());
+}
+
+#[test]
+fn append_comment_new_section() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[A]",  "\n"));
+    lines.push(super::IniLine::new("x=1",  "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::dotenv());
+
+    file.set_edit_options(super::IniEditOptions { comment: Some("  added  ".to_string()), ..Default::default() });
+    file.edit("B", "y", "2", false, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,                 file.lines.len());
+    assert_eq!("[B]",             file.lines[2].content.to_string());
+    assert_eq!("# inied: added",  file.lines[3].content.to_string());
+    assert_eq!("y=2",             file.lines[4].content.to_string());
+}
+
+#[test]
+fn edit_dotenv() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("# Settings",          "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("export A=1 # First", "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("B='2'",               "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("export D=\"a b\" # c", "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("",                    "\n", &super::IniDialect::dotenv()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::dotenv());
+
+    file.edit("", "A", "10", true, true);
+    file.edit("", "C", "30", true, true);
+    file.edit("", "D", "new val", true, true);
+    file.delete(Some(""), Some("B"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,                             file.lines.len());
+    assert_eq!("# Settings",                  file.lines[0].content.to_string());
+    assert_eq!("export A=10 # First",         file.lines[1].content.to_string());
+    assert_eq!("export D=\"new val\" # c",    file.lines[2].content.to_string());
+    assert_eq!("C=30",                        file.lines[3].content.to_string());
+    assert_eq!("",                            file.lines[4].content.to_string());
+}
+
+#[test]
+fn edit_dotenv_shell_quoting() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("export A=1", "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("B=2",        "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("C=3",        "\n", &super::IniDialect::dotenv()));
+    lines.push(super::IniLine::parse("D=4",        "\n", &super::IniDialect::dotenv()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::dotenv());
+
+    file.edit("", "A", "new val", true, true);
+    file.edit("", "B", "$HOME/bin", true, true);
+    file.edit("", "C", "it's $HOME", true, true);
+    file.edit("", "D", "a;b", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(4,                             file.lines.len());
+    assert_eq!("export A=\"new val\"",        file.lines[0].content.to_string());
+    assert_eq!("B='$HOME/bin'",               file.lines[1].content.to_string());
+    assert_eq!("C=\"it's \\$HOME\"",          file.lines[2].content.to_string());
+    assert_eq!("D=\"a;b\"",                   file.lines[3].content.to_string());
+}
+
+#[test]
+fn systemd_drop_in_directories() {
+    assert_eq!(vec!["service.d", "foo.service.d"],                                                  super::systemd::drop_in_directories("foo.service"));
+    assert_eq!(vec!["service.d", "foo-.service.d", "foo-bar-.service.d", "foo-bar-baz.service.d"], super::systemd::drop_in_directories("foo-bar-baz.service"));
+    assert_eq!(vec!["service.d", "getty@.service.d", "getty@tty1.service.d"],                       super::systemd::drop_in_directories("getty@tty1.service"));
+}
+
+#[test]
+fn systemd_drop_in_files_override() {
+    let directory = create_files("drop-ins", &[
+        ("foo-bar.service",                "[Service]\nExecStart=/a\nUser=a\n"),
+        ("service.d/10-all.conf",          "[Service]\nUser=b\n"),
+        ("foo-.service.d/10-all.conf",     "[Service]\nUser=c\n"),
+        ("foo-.service.d/20-prefix.conf",  "[Service]\nExecStart=\nExecStart=/d\n"),
+        ("foo-bar.service.d/10-all.conf",  "[Service]\nUser=e\n"),
+        ("foo-bar.service.d/30-skip.txt",  "[Service]\nUser=f\n"),
+    ]);
+
+    let file_name = directory.join("foo-bar.service");
+    let drop_ins = super::systemd::drop_in_files(file_name.to_str().unwrap()).unwrap();
+    assert_eq!(2,                                                drop_ins.len());
+    assert_eq!(directory.join("foo-bar.service.d/10-all.conf"), drop_ins[0]); //the same name in more specific directory wins
+    assert_eq!(directory.join("foo-.service.d/20-prefix.conf"), drop_ins[1]);
+
+    let mut file = super::IniFile::parse_effective(file_name.to_str().unwrap(), &super::IniDialect::systemd(), None, false).unwrap_or_else(|err| panic!("{}", err));
+    file.resolve_resets();
+    file.filter(Some("Service"), None);
+    print(&file);
+
+    let values: Vec<String> = file.lines.iter().filter_map(|line| {
+        match &line.content {
+            super::IniContent::Entry(entry) => Some(entry.to_string()),
+            _                               => None,
+        }
+    }).collect();
+    assert_eq!(vec!["User=a", "User=e", "ExecStart=/d"], values);
+}
+
+#[test]
+fn edit_systemd_duplicates() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[Service]",       "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStart=",      "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStart=/b",    "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("Environment=A=1", "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("Environment=B=2", "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStop=/d",     "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStop=",       "",   &super::IniDialect::systemd()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::systemd());
+
+    file.edit("Service", "ExecStart", "/c", true, true);
+    file.edit("Service", "Environment", "C=3", true, true);
+    file.edit("Service", "ExecStop", "/e", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(8,                 file.lines.len());
+    assert_eq!("ExecStart=",      file.lines[1].content.to_string());
+    assert_eq!("ExecStart=/c",    file.lines[2].content.to_string());
+    assert_eq!("Environment=A=1", file.lines[3].content.to_string());
+    assert_eq!("Environment=C=3", file.lines[4].content.to_string());
+    assert_eq!("ExecStop=/d",     file.lines[5].content.to_string());
+    assert_eq!("ExecStop=",       file.lines[6].content.to_string());
+    assert_eq!("\n",              file.lines[6].line_ending);
+    assert_eq!("ExecStop=/e",     file.lines[7].content.to_string());
+    assert_eq!("",                file.lines[7].line_ending);
+}
+
+#[test]
+fn resolve_resets_systemd() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[Service]",      "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStartPre=A", "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStartPre=B", "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("[Unit]",         "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStartPre=C", "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("[Service]",      "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStartPre=",  "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStartPre=D", "\n", &super::IniDialect::systemd()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::systemd());
+
+    file.resolve_resets();
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,                file.lines.len());
+    assert_eq!("[Service]",      file.lines[0].content.to_string());
+    assert_eq!("[Unit]",         file.lines[1].content.to_string());
+    assert_eq!("ExecStartPre=C", file.lines[2].content.to_string());
+    assert_eq!("[Service]",      file.lines[3].content.to_string());
+    assert_eq!("ExecStartPre=D", file.lines[4].content.to_string());
+}
+
+#[test]
+fn resolve_resets_ini() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
+    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "")),  line_ending: "\n".to_string() });
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.resolve_resets();
+    let file = file;
+    print(&file);
+
+    assert_eq!(3,      file.lines.len());
+}
+
+#[test]
+fn filter_subsection() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[remote \"origin\"]",   "\n"));
+    lines.push(super::IniLine::new("url = A",                 "\n"));
+    lines.push(super::IniLine::new("[remote \"upstream\"]", "\n"));
+    lines.push(super::IniLine::new("url = B",                 "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.filter(Some("remote.upstream"), Some("url"));
+    let file = file;
+    print(&file);
+
+    assert_eq!(1,         file.lines.len());
+    assert_eq!("url = B", file.lines[0].content.to_string());
+}
+
+#[test]
+fn edit_subsection() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[remote \"origin\"]", "\n"));
+    lines.push(super::IniLine::new("url = A",               "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("remote \"origin\"", "url", "B", true, true);
+    file.edit("remote.origin", "fetch", "C", true, true);
+    file.edit("remote.up\\str\"eam", "url", "D", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(5,                            file.lines.len());
+    assert_eq!("[remote \"origin\"]",        file.lines[0].content.to_string());
+    assert_eq!("url = B",                    file.lines[1].content.to_string());
+    assert_eq!("fetch=C",                    file.lines[2].content.to_string());
+    assert_eq!("[remote \"up\\\\str\\\"eam\"]", file.lines[3].content.to_string());
+    assert_eq!("url=D",                      file.lines[4].content.to_string());
+}
+
+#[test]
+fn edit_subsection_without_subsections() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[remote]", "\n"));
+    lines.push(super::IniLine::new("url = A",  "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.edit("remote.origin", "url", "B", true, true);
+    let file = file;
+    print(&file);
+
+    assert_eq!(4,                 file.lines.len());
+    assert_eq!("[remote.origin]", file.lines[2].content.to_string());
+    assert_eq!("url=B",           file.lines[3].content.to_string());
+}
+
+#[test]
+fn filter_localized() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[Desktop Entry]", "\n", &super::IniDialect::desktop()));
+    lines.push(super::IniLine::parse("Name=A",          "\n", &super::IniDialect::desktop()));
+    lines.push(super::IniLine::parse("Name[de]=B",      "\n", &super::IniDialect::desktop()));
+    lines.push(super::IniLine::parse("Name[de_AT]=C",   "\n", &super::IniDialect::desktop()));
+    lines.push(super::IniLine::parse("GenericName=D",   "\n", &super::IniDialect::desktop()));
+    let file = super::IniFile::new(lines, super::IniDialect::desktop());
+
+    for (locale, value) in &[("de_AT.UTF-8", "Name[de_AT]=C"), ("de_CH", "Name[de]=B"), ("de", "Name[de]=B"), ("fr_FR", "Name=A")] {
+        let mut file = super::IniFile::new(file.lines.clone(), super::IniDialect::desktop());
+        file.filter_localized(Some("Desktop Entry"), "Name", locale);
+        let file = file;
+        print(&file);
+
+        assert_eq!(1,     file.lines.len());
+        assert_eq!(*value, file.lines[0].content.to_string());
+    }
+}
+
+#[test]
+fn edit_list() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[Desktop Entry]",    "\n", &super::IniDialect::desktop()));
+    lines.push(super::IniLine::parse("Categories=A;B;",    "\n", &super::IniDialect::desktop()));
+    lines.push(super::IniLine::parse("Keywords=A\\;B;C", "\n", &super::IniDialect::desktop()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::desktop());
+
+    file.edit_list("Desktop Entry", "Categories", "C", true);
+    file.edit_list("Desktop Entry", "Categories", "A", true);
+    file.edit_list("Desktop Entry", "Categories", "B", false);
+    file.edit_list("Desktop Entry", "Keywords", "A;B", false);
+    file.edit_list("Desktop Entry", "MimeType", "text/plain", true);
+    file.edit_list("Desktop Entry", "Actions", "X", false);
+    let file = file;
+    print(&file);
+
+    assert_eq!(4,                     file.lines.len());
+    assert_eq!("Categories=A;C;",     file.lines[1].content.to_string());
+    assert_eq!("Keywords=C",          file.lines[2].content.to_string());
+    assert_eq!("MimeType=text/plain;", file.lines[3].content.to_string());
+}
+
+#[test]
+fn edit_reg() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("REGEDIT4",                "\r\n", &super::IniDialect::reg()));
+    lines.push(super::IniLine::parse("[HKEY_CURRENT_USER\\A]",  "\r\n", &super::IniDialect::reg()));
+    lines.push(super::IniLine::parse("\"Path\"=\"C:\\\\\"",     "\r\n", &super::IniDialect::reg()));
+    lines.push(super::IniLine::parse("\"Count\"=dword:00000001", "\r\n", &super::IniDialect::reg()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::reg());
+
+    file.edit("HKEY_CURRENT_USER\\A", "Path", "D:\\\"x\"", true, true);
+    file.edit("HKEY_CURRENT_USER\\A", "Count", "dword:00000002", true, true);
+    file.edit("HKEY_CURRENT_USER\\A", "@", "Default", true, true);
+    file.edit("HKEY_CURRENT_USER\\A", "Old Name", "-", true, true);
     let file = file;
     print(&file);
 
-    assert_eq!(10,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=1",  file.lines[1].content.to_string());
-    assert_eq!("A=10", file.lines[2].content.to_string());
-    assert_eq!("C=30", file.lines[3].content.to_string());
-    assert_eq!("[Y]",  file.lines[4].content.to_string());
-    assert_eq!("B=2",  file.lines[5].content.to_string());
-    assert_eq!("B=20", file.lines[6].content.to_string());
-    assert_eq!("D=40", file.lines[7].content.to_string());
-    assert_eq!("[Z]",  file.lines[8].content.to_string());
-    assert_eq!("E=50", file.lines[9].content.to_string());
+    assert_eq!(6,                              file.lines.len());
+    assert_eq!(r#""Path"="D:\\\"x\"""#,        file.lines[2].content.to_string());
+    assert_eq!(r#""Count"=dword:00000002"#,    file.lines[3].content.to_string());
+    assert_eq!(r#"@="Default""#,               file.lines[4].content.to_string());
+    assert_eq!(r#""Old Name"=-"#,              file.lines[5].content.to_string());
 }
 
 #[test]
-fn append_with_spacing() {
+fn lint() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    lines.push(super::IniLine::new("top=1",         "\n"));
+    lines.push(super::IniLine::new("[mysqld",       "\n"));
+    lines.push(super::IniLine::new("[A]",           "\n"));
+    lines.push(super::IniLine::new("  x=1",         "\r\n"));
+    lines.push(super::IniLine::new("key_buffer 1M", "\n"));
+    lines.push(super::IniLine::new("  x=2",         "\n"));
+    lines.push(super::IniLine::new("",              "\n"));
+    lines.push(super::IniLine::new(" [A]",          "\n"));
+    lines.push(super::IniLine::new("x=3",           ""));
+    let file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    let issues: Vec<String> = file.lint().iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(7, issues.len());
+    assert_eq!("1:1: warning: key 'top' is outside of any section",                     issues[0]);
+    assert_eq!("2:1: error: section is not closed",                                     issues[1]);
+    assert_eq!("4:1: warning: line ending CRLF differs from LF used by the first line", issues[2]);
+    assert_eq!("5:1: error: line is not a section, an entry, or a comment",             issues[3]);
+    assert_eq!("6:3: warning: key 'x' is already defined on line 4",                    issues[4]);
+    assert_eq!("8:2: warning: section 'A' is already defined on line 3",                issues[5]);
+    assert_eq!("9:1: warning: key 'x' is already defined on line 4",                    issues[6]);
+}
 
-    file.edit("X", "A", "10", false, true);
-    file.edit("Y", "B", "20", false, true);
-    file.edit("X", "C", "30", false, true);
-    file.edit("Y", "D", "40", false, true);
-    file.edit("Z", "E", "50", false, true);
+#[test]
+fn dedupe_keep_last() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[A]",       "\n"));
+    lines.push(super::IniLine::new("x=1",       "\n"));
+    lines.push(super::IniLine::new("; old y",   "\n"));
+    lines.push(super::IniLine::new("y=2",       "\n"));
+    lines.push(super::IniLine::new("",          "\n"));
+    lines.push(super::IniLine::new("[B]",       "\n"));
+    lines.push(super::IniLine::new("z=3",       "\n"));
+    lines.push(super::IniLine::new("",          "\n"));
+    lines.push(super::IniLine::new("; again",   "\n"));
+    lines.push(super::IniLine::new("[A]",       "\n"));
+    lines.push(super::IniLine::new("y=4",       "\n"));
+    lines.push(super::IniLine::new("y=5",       ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    let issues: Vec<String> = file.dedupe(super::IniDedupe::KeepLast).iter().map(|issue| issue.to_string()).collect();
     let file = file;
     print(&file);
 
-    assert_eq!(13,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=1",  file.lines[1].content.to_string());
-    assert_eq!("#-",   file.lines[2].content.to_string());
-    assert_eq!("A=10", file.lines[3].content.to_string());
-    assert_eq!("C=30", file.lines[4].content.to_string());
-    assert_eq!("",     file.lines[5].content.to_string());
-    assert_eq!("[Y]",  file.lines[6].content.to_string());
-    assert_eq!("B=2",  file.lines[7].content.to_string());
-    assert_eq!("B=20", file.lines[8].content.to_string());
-    assert_eq!("D=40", file.lines[9].content.to_string());
-    assert_eq!("",     file.lines[10].content.to_string());
-    assert_eq!("[Z]",  file.lines[11].content.to_string());
-    assert_eq!("E=50", file.lines[12].content.to_string());
+    assert_eq!(3, issues.len());
+    assert_eq!("4:1: warning: key 'y' removed in favor of line 12",                           issues[0]);
+    assert_eq!("10:1: warning: section 'A' merged into line 1 and comments above it removed", issues[1]);
+    assert_eq!("11:1: warning: key 'y' removed in favor of line 12",                          issues[2]);
+
+    assert_eq!(6,      file.lines.len());
+    assert_eq!("[A]",  file.lines[0].content.to_string());
+    assert_eq!("x=1",  file.lines[1].content.to_string());
+    assert_eq!("y=5",  file.lines[2].content.to_string());
+    assert_eq!("",     file.lines[3].content.to_string());
+    assert_eq!("[B]",  file.lines[4].content.to_string());
+    assert_eq!("z=3",  file.lines[5].content.to_string());
+    assert_eq!("",     file.lines[5].line_ending);
 }
 
 #[test]
-fn append_with_multi_spacing() {
+fn dedupe_empty_lines() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
-
-    file.edit("X", "A", "10", false, true);
-    file.edit("Y", "B", "20", false, true);
-    file.edit("X", "C", "30", false, true);
-    file.edit("Y", "D", "40", false, true);
-    file.edit("Z", "E", "50", false, true);
+    lines.push(super::IniLine::new("[b]",  "\n"));
+    lines.push(super::IniLine::new("k=1",  "\n"));
+    lines.push(super::IniLine::new("",     "\n"));
+    lines.push(super::IniLine::new("[a]",  "\n"));
+    lines.push(super::IniLine::new("x=1",  "\n"));
+    lines.push(super::IniLine::new("",     "\n"));
+    lines.push(super::IniLine::new("[b]",  "\n"));
+    lines.push(super::IniLine::new("k=2",  "\n"));
+    lines.push(super::IniLine::new("",     "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    file.dedupe(super::IniDedupe::KeepLast);
     let file = file;
     print(&file);
 
-    assert_eq!(16,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=1",  file.lines[1].content.to_string());
-    assert_eq!("#-",   file.lines[2].content.to_string());
-    assert_eq!("A=10", file.lines[3].content.to_string());
-    assert_eq!("C=30", file.lines[4].content.to_string());
+    assert_eq!(6,      file.lines.len());
+    assert_eq!("[b]",  file.lines[0].content.to_string());
+    assert_eq!("k=2",  file.lines[1].content.to_string());
+    assert_eq!("",     file.lines[2].content.to_string());
+    assert_eq!("[a]",  file.lines[3].content.to_string());
+    assert_eq!("x=1",  file.lines[4].content.to_string());
     assert_eq!("",     file.lines[5].content.to_string());
-    assert_eq!("",     file.lines[6].content.to_string());
-    assert_eq!("",     file.lines[7].content.to_string());
-    assert_eq!("[Y]",  file.lines[8].content.to_string());
-    assert_eq!("B=2",  file.lines[9].content.to_string());
-    assert_eq!("B=20", file.lines[10].content.to_string());
-    assert_eq!("D=40", file.lines[11].content.to_string());
-    assert_eq!("",     file.lines[12].content.to_string());
-    assert_eq!("",     file.lines[13].content.to_string());
-    assert_eq!("[Z]",  file.lines[14].content.to_string());
-    assert_eq!("E=50", file.lines[15].content.to_string());
 }
 
 #[test]
-fn change_no_spacing() {
+fn dedupe_keep_first() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
-
-    file.edit("X", "A", "10", true, false);
-    file.edit("Y", "B", "20", true, false);
-    file.edit("X", "C", "30", true, false);
-    file.edit("Y", "D", "40", true, false);
-    file.edit("Z", "E", "50", true, false);
+    lines.push(super::IniLine::new("[A]",  "\n"));
+    lines.push(super::IniLine::new("x=1",  "\n"));
+    lines.push(super::IniLine::new("[a]",  "\n"));
+    lines.push(super::IniLine::new("X=2",  "\n"));
+    lines.push(super::IniLine::new("y=3",  "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::windows());
+
+    let issues: Vec<String> = file.dedupe(super::IniDedupe::KeepFirst).iter().map(|issue| issue.to_string()).collect();
     let file = file;
     print(&file);
 
-    assert_eq!(4,      file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=10", file.lines[1].content.to_string());
-    assert_eq!("[Y]",  file.lines[2].content.to_string());
-    assert_eq!("B=20", file.lines[3].content.to_string());
+    assert_eq!(2, issues.len());
+    assert_eq!("3:1: warning: section 'a' merged into line 1",    issues[0]);
+    assert_eq!("4:1: warning: key 'X' removed in favor of line 2", issues[1]);
+
+    assert_eq!(3,      file.lines.len());
+    assert_eq!("x=1",  file.lines[1].content.to_string());
+    assert_eq!("y=3",  file.lines[2].content.to_string());
 }
 
 #[test]
-fn change_with_spacing() {
+fn dedupe_error() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    lines.push(super::IniLine::new("[A]",  "\n"));
+    lines.push(super::IniLine::new("x=1",  "\n"));
+    lines.push(super::IniLine::new("x=2",  "\n"));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
 
-    file.edit("X", "A", "10", true, false);
-    file.edit("Y", "B", "20", true, false);
-    file.edit("X", "C", "30", true, false);
-    file.edit("Y", "D", "40", true, false);
-    file.edit("Z", "E", "50", true, false);
+    let issues: Vec<String> = file.dedupe(super::IniDedupe::Error).iter().map(|issue| issue.to_string()).collect();
     let file = file;
     print(&file);
 
-    assert_eq!(7,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=10", file.lines[1].content.to_string());
-    assert_eq!("#-",   file.lines[2].content.to_string());
-    assert_eq!("",     file.lines[3].content.to_string());
-    assert_eq!("[Y]",  file.lines[4].content.to_string());
-    assert_eq!("B=20", file.lines[5].content.to_string());
-    assert_eq!("",     file.lines[6].content.to_string());
+    assert_eq!(1, issues.len());
+    assert_eq!("3:1: error: key 'x' is already defined on line 2", issues[0]);
+    assert_eq!(3, file.lines.len());
 }
 
 #[test]
-fn change_with_multi_spacing() {
+fn overlay() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
-
-    file.edit("X", "A", "10", true, false);
-    file.edit("Y", "B", "20", true, false);
-    file.edit("X", "C", "30", true, false);
-    file.edit("Y", "D", "40", true, false);
-    file.edit("Z", "E", "50", true, false);
+    lines.push(super::IniLine::new("[X]",          "\r\n"));
+    lines.push(super::IniLine::new("A = 1",        "\r\n"));
+    lines.push(super::IniLine::new("B = 2",        "\r\n"));
+    lines.push(super::IniLine::new("",             "\r\n"));
+    lines.push(super::IniLine::new("[Y]",          "\r\n"));
+    lines.push(super::IniLine::new("C = 3",        ""));
+    let mut file = super::IniFile::new(lines, super::IniDialect::ini());
+
+    let mut other_lines = Vec::new();
+    other_lines.push(super::IniLine::new("[X]",            "\n"));
+    other_lines.push(super::IniLine::new("B=\"two\"",      "\n"));
+    other_lines.push(super::IniLine::new("; about D",      "\n"));
+    other_lines.push(super::IniLine::new("D=4",            "\n"));
+    other_lines.push(super::IniLine::new("[Z]",            "\n"));
+    other_lines.push(super::IniLine::new("E=5",            ""));
+    let other = super::IniFile::new(other_lines, super::IniDialect::ini());
+
+    file.overlay(&other);
     let file = file;
     print(&file);
 
-    assert_eq!(10,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=10", file.lines[1].content.to_string());
-    assert_eq!("#-",   file.lines[2].content.to_string());
-    assert_eq!("",     file.lines[3].content.to_string());
-    assert_eq!("",     file.lines[4].content.to_string());
-    assert_eq!("",     file.lines[5].content.to_string());
-    assert_eq!("[Y]",  file.lines[6].content.to_string());
-    assert_eq!("B=20", file.lines[7].content.to_string());
-    assert_eq!("",     file.lines[8].content.to_string());
-    assert_eq!("",     file.lines[9].content.to_string());
+    assert_eq!(11,            file.lines.len());
+    assert_eq!("A = 1",       file.lines[1].content.to_string());
+    assert_eq!("B = \"two\"", file.lines[2].content.to_string());
+    assert_eq!("; about D",   file.lines[3].content.to_string());
+    assert_eq!("D=4",         file.lines[4].content.to_string());
+    assert_eq!("\r\n",        file.lines[4].line_ending);
+    assert_eq!("",            file.lines[5].content.to_string());
+    assert_eq!("[Y]",         file.lines[6].content.to_string());
+    assert_eq!("C = 3",       file.lines[7].content.to_string());
+    assert_eq!("",            file.lines[8].content.to_string());
+    assert_eq!("[Z]",         file.lines[9].content.to_string());
+    assert_eq!("E=5",         file.lines[10].content.to_string());
+    assert_eq!("",            file.lines[10].line_ending);
 }
 
 #[test]
-fn edit_no_spacing() {
+fn lint_systemd_continuation() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    lines.push(super::IniLine::parse("[Service]",                  "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStart=a \\\n  b \\\n  c", "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStart=d",                "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("[Service]",                  "\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("Invalid",                    "\n", &super::IniDialect::systemd()));
+    let file = super::IniFile::new(lines, super::IniDialect::systemd());
+
+    let issues: Vec<String> = file.lint().iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(1, issues.len()); //duplicates are allowed
+    assert_eq!("7:1: error: line is not a section, an entry, or a comment", issues[0]);
+}
 
-    file.edit("X", "A", "10", true, true);
-    file.edit("Y", "B", "20", true, true);
-    file.edit("X", "C", "30", true, true);
-    file.edit("Y", "D", "40", true, true);
-    file.edit("Z", "E", "50", true, true);
+#[test]
+fn set_line_endings() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[Service]",                "\r\n", &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("ExecStart=a \\\r\n  b",    "\n",   &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("# Comment",                "\n",   &super::IniDialect::systemd()));
+    lines.push(super::IniLine::parse("Type=simple",              "",     &super::IniDialect::systemd()));
+    let mut file = super::IniFile::new(lines, super::IniDialect::systemd());
+
+    assert_eq!("\n", file.get_common_line_ending());
+    file.set_line_endings("\r\n");
     let file = file;
     print(&file);
 
-    assert_eq!(8,      file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=10", file.lines[1].content.to_string());
-    assert_eq!("C=30", file.lines[2].content.to_string());
-    assert_eq!("[Y]",  file.lines[3].content.to_string());
-    assert_eq!("B=20", file.lines[4].content.to_string());
-    assert_eq!("D=40", file.lines[5].content.to_string());
-    assert_eq!("[Z]",  file.lines[6].content.to_string());
-    assert_eq!("E=50", file.lines[7].content.to_string());
+    assert_eq!(4, file.lines.len());
+    for line in &file.lines {
+        assert_eq!("\r\n", line.line_ending);
+    }
+    assert_eq!("ExecStart=a \\\r\n  b", file.lines[1].content.to_string());
+    assert_eq!("\r\n", file.get_common_line_ending());
 }
 
 #[test]
-fn edit_with_spacing() {
+fn get_common_line_ending() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    lines.push(super::IniLine::new("[A]", "\r\n"));
+    lines.push(super::IniLine::new("a=1", "\n"));
+    lines.push(super::IniLine::new("b=2", ""));
+    let file = super::IniFile::new(lines, super::IniDialect::ini());
+    assert_eq!("\r\n", file.get_common_line_ending()); //first one wins on tie
+
+    let file = super::IniFile::new(Vec::new(), super::IniDialect::ini());
+    assert_eq!("\n", file.get_common_line_ending());
+}
 
-    file.edit("X", "A", "10", true, true);
-    file.edit("Y", "B", "20", true, true);
-    file.edit("X", "C", "30", true, true);
-    file.edit("Y", "D", "40", true, true);
-    file.edit("Z", "E", "50", true, true);
+#[test]
+fn edit_ignore_case() {
+    let mut dialect = super::IniDialect::ini();
+    dialect.set_ignore_case();
+
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::new("[MySection]", "\n"));
+    lines.push(super::IniLine::new("MyKey=1",     "\n"));
+    lines.push(super::IniLine::new("Other=2",     "\n"));
+    let mut file = super::IniFile::new(lines, dialect);
+
+    file.edit("MYSECTION", "mykey", "10", true, true);
+    file.edit("mysection", "NEW", "20", true, true);
+    file.delete(Some("mySection"), Some("OTHER"));
     let file = file;
     print(&file);
 
-    assert_eq!(11,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=10", file.lines[1].content.to_string());
-    assert_eq!("#-",   file.lines[2].content.to_string());
-    assert_eq!("C=30", file.lines[3].content.to_string());
-    assert_eq!("",     file.lines[4].content.to_string());
-    assert_eq!("[Y]",  file.lines[5].content.to_string());
-    assert_eq!("B=20", file.lines[6].content.to_string());
-    assert_eq!("D=40", file.lines[7].content.to_string());
-    assert_eq!("",     file.lines[8].content.to_string());
-    assert_eq!("[Z]",  file.lines[9].content.to_string());
-    assert_eq!("E=50", file.lines[10].content.to_string());
+    assert_eq!(3,             file.lines.len());
+    assert_eq!("[MySection]", file.lines[0].content.to_string());
+    assert_eq!("MyKey=10",    file.lines[1].content.to_string());
+    assert_eq!("NEW=20",      file.lines[2].content.to_string());
 }
 
 #[test]
-fn edit_with_multi_spacing() {
+fn filter_ignore_case() {
     let mut lines = Vec::new();
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("X")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("A", "1")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Comment(super::IniComment::create("#", "-")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Section(super::IniSection::create("Y")),      line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Entry  (  super::IniEntry::create("B", "2")), line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    lines.push(super::IniLine { content: super::IniContent::Other  (  super::IniOther::create("")),       line_ending: "\n".to_string() });
-    let mut file = super::IniFile { lines: lines };
+    lines.push(super::IniLine::new("[A]", "\n"));
+    lines.push(super::IniLine::new("x=1", "\n"));
+    lines.push(super::IniLine::new("X=2", "\n"));
+    let exact_lines = lines.clone();
+
+    let mut dialect = super::IniDialect::ini();
+    dialect.set_ignore_case();
+    let mut file = super::IniFile::new(lines, dialect);
+    file.filter(Some("a"), Some("x"));
+    assert_eq!(2, file.lines.len());
+
+    let mut file = super::IniFile::new(exact_lines, super::IniDialect::ini());
+    file.filter(Some("A"), Some("x"));
+    assert_eq!(1, file.lines.len());
+}
 
-    file.edit("X", "A", "10", true, true);
-    file.edit("Y", "B", "20", true, true);
-    file.edit("X", "C", "30", true, true);
-    file.edit("Y", "D", "40", true, true);
-    file.edit("Z", "E", "50", true, true);
+#[test]
+fn lint_ignore_case() {
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[HKEY_CURRENT_USER\\A]", "\n", &super::IniDialect::reg()));
+    lines.push(super::IniLine::parse("\"Name\"=\"1\"",        "\n", &super::IniDialect::reg()));
+    lines.push(super::IniLine::parse("[hkey_current_user\\a]", "\n", &super::IniDialect::reg()));
+    lines.push(super::IniLine::parse("\"NAME\"=\"2\"",        "\n", &super::IniDialect::reg()));
+    let file = super::IniFile::new(lines, super::IniDialect::reg());
+
+    let issues: Vec<String> = file.lint().iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(2, issues.len());
+    assert_eq!("3:1: warning: section 'hkey_current_user\\a' is already defined on line 1", issues[0]);
+    assert_eq!("4:1: warning: key 'NAME' is already defined on line 2",                     issues[1]);
+}
+
+#[test]
+fn parse_mysql_includes() {
+    let directory = create_files("includes", &[
+        ("my.cnf",       "[mysqld]\nport=1\n!include extra.cnf\nuser=a\n!includedir conf.d\n"),
+        ("extra.cnf",    "[client]\nport=2\n"),
+        ("conf.d/b.cnf", "[mysqld]\nport=4\n"),
+        ("conf.d/a.cnf", "[mysqld]\nport=3\n"),
+        ("conf.d/c.txt", "[mysqld]\nport=9\n"),
+    ]);
+
+    let file_name = directory.join("my.cnf");
+    let mut file = super::IniFile::parse_effective(file_name.to_str().unwrap(), &super::IniDialect::mysql(), None, false).unwrap_or_else(|err| panic!("{}", err));
+    file.filter(Some("mysqld"), None);
+    print(&file);
+
+    let values: Vec<String> = file.lines.iter().filter_map(|line| {
+        match &line.content {
+            super::IniContent::Entry(entry) => Some(entry.to_string()),
+            _                               => None,
+        }
+    }).collect();
+    assert_eq!(vec!["port=1", "user=a", "port=3", "port=4"], values); //section is restored after include and only *.cnf files are read in order
+}
+
+#[test]
+fn parse_mysql_include_depth() {
+    let directory = create_files("include-depth", &[("loop.cnf", "[a]\nx=1\n!include loop.cnf\n")]);
+
+    let file_name = directory.join("loop.cnf");
+    let file = super::IniFile::parse_effective(file_name.to_str().unwrap(), &super::IniDialect::mysql(), None, false).unwrap_or_else(|err| panic!("{}", err));
+
+    let count = file.lines.iter().filter(|line| matches!(line.content, super::IniContent::Entry(_))).count();
+    assert_eq!(super::mysql::MAX_INCLUDE_DEPTH + 1, count);
+}
+
+#[test]
+fn edit_mysql() {
+    let dialect = super::IniDialect::mysql();
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[mysqld]",                "\n", &dialect));
+    lines.push(super::IniLine::parse("max-connections=8",       "\n", &dialect));
+    lines.push(super::IniLine::parse("skip-networking",         "\n", &dialect));
+    lines.push(super::IniLine::parse("!includedir /etc/my.cnf.d", "\n", &dialect));
+    let mut file = super::IniFile::new(lines, dialect);
+
+    file.edit("mysqld", "max_connections", "100", true, true);
+    file.edit("mysqld", "loose-skip_networking", "1", true, true);
+    print(&file);
+
+    assert_eq!(4,                           file.lines.len());
+    assert_eq!("max-connections=100",       file.lines[1].content.to_string());
+    assert_eq!("skip-networking=1",         file.lines[2].content.to_string());
+
+    file.delete(Some("mysqld"), None); //directive doesn't belong to section
     let file = file;
     print(&file);
 
-    assert_eq!(14,     file.lines.len());
-    assert_eq!("[X]",  file.lines[0].content.to_string());
-    assert_eq!("A=10", file.lines[1].content.to_string());
-    assert_eq!("#-",   file.lines[2].content.to_string());
-    assert_eq!("C=30", file.lines[3].content.to_string());
-    assert_eq!("",     file.lines[4].content.to_string());
-    assert_eq!("",     file.lines[5].content.to_string());
-    assert_eq!("",     file.lines[6].content.to_string());
-    assert_eq!("[Y]",  file.lines[7].content.to_string());
-    assert_eq!("B=20", file.lines[8].content.to_string());
-    assert_eq!("D=40", file.lines[9].content.to_string());
-    assert_eq!("",     file.lines[10].content.to_string());
-    assert_eq!("",     file.lines[11].content.to_string());
-    assert_eq!("[Z]",  file.lines[12].content.to_string());
-    assert_eq!("E=50", file.lines[13].content.to_string());
+    assert_eq!(1,                           file.lines.len());
+    assert_eq!("!includedir /etc/my.cnf.d", file.lines[0].content.to_string());
+}
+
+#[test]
+fn lint_mysql() {
+    let dialect = super::IniDialect::mysql();
+    let mut lines = Vec::new();
+    lines.push(super::IniLine::parse("[mysqld]",         "\n", &dialect));
+    lines.push(super::IniLine::parse("skip-networking",  "\n", &dialect));
+    lines.push(super::IniLine::parse("skip_networking",  "\n", &dialect));
+    lines.push(super::IniLine::parse("!includes /etc",   "\n", &dialect));
+    let file = super::IniFile::new(lines, dialect);
+
+    let issues: Vec<String> = file.lint().iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(2, issues.len());
+    assert_eq!("3:1: warning: key 'skip_networking' is already defined on line 2", issues[0]);
+    assert_eq!("4:1: error: unknown directive",                                    issues[1]);
 }
 
 
@@ -1113,9 +3088,21 @@ fn print(file: &super::IniFile) {
             super::IniContent::Section(_) => { print!(" S: "); },
             super::IniContent::Entry(_)   => { print!(" E: "); },
             super::IniContent::Comment(_) => { print!(" C: "); },
+            super::IniContent::Directive(_) => { print!(" D: "); },
             super::IniContent::Other(_)   => { print!(" O: "); },
         }
         line_number += 1;
         println!("{}", line.get_content());
     }
 }
+
+fn create_files(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf { //fresh directory with given files
+    let directory = std::env::temp_dir().join(format!("inied-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    for (file_name, content) in files {
+        let path = directory.join(file_name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    directory
+}
@@ -1,8 +1,9 @@
 extern crate clap;
-use clap::{Arg, App};
+use clap::{Arg, App, ArgMatches};
+use std::io::Write;
 
 mod ini;
-use crate::ini::{IniFile, IniContent};
+use crate::ini::{IniFile, IniContent, IniDedupe, IniDialect, IniDuplicates, IniEditOptions, IniEncoding, IniError, IniPosition, IniSectionPosition};
 
 
 const CARGO_NAME: &'static str = env!("CARGO_PKG_NAME");
@@ -25,6 +26,13 @@ fn main() {
                     .long("key")
                     .takes_value(true)
                     .help("Key to show or replace"))
+                .arg(Arg::with_name("ignorecase")
+                    .long("ignore-case")
+                    .help("Section and key are matched without regard to case"))
+                .arg(Arg::with_name("locale")
+                    .long("locale")
+                    .takes_value(true)
+                    .help("Locale of key to show or replace"))
                 .arg(Arg::with_name("print")
                     .short("p")
                     .long("print")
@@ -33,6 +41,15 @@ fn main() {
                     .short("r")
                     .long("print-raw")
                     .help("Show value without removing quotes"))
+                .arg(Arg::with_name("withdocs")
+                    .long("with-docs")
+                    .help("Show comments directly above entry too"))
+                .arg(Arg::with_name("exists")
+                    .long("exists")
+                    .help("Checks if section or key exists"))
+                .arg(Arg::with_name("lint")
+                    .long("lint")
+                    .help("Reports problems found in file"))
                 .arg(Arg::with_name("delete")
                     .short("d")
                     .long("delete")
@@ -52,6 +69,54 @@ fn main() {
                     .long("edit")
                     .takes_value(true)
                     .help("Value will be changed if exists or added if it doesn't"))
+                .arg(Arg::with_name("copyto")
+                    .long("copy-to")
+                    .takes_value(true)
+                    .help("Entry will be copied to another section together with its comments"))
+                .arg(Arg::with_name("moveto")
+                    .long("move-to")
+                    .takes_value(true)
+                    .help("Entry will be moved to another section together with its comments"))
+                .arg(Arg::with_name("addflag")
+                    .long("add-flag")
+                    .help("Key without value will be added if it doesn't exist"))
+                .arg(Arg::with_name("additem")
+                    .long("add-item")
+                    .takes_value(true)
+                    .help("Item will be added to list value if it doesn't exist"))
+                .arg(Arg::with_name("removeitem")
+                    .long("remove-item")
+                    .takes_value(true)
+                    .help("Item will be removed from list value"))
+                .arg(Arg::with_name("raw")
+                    .long("raw")
+                    .help("Value is written as given, without quoting or escaping"))
+                .arg(Arg::with_name("comment")
+                    .long("comment")
+                    .takes_value(true)
+                    .help("Comment will be added above new or changed entry"))
+                .arg(Arg::with_name("before")
+                    .long("before")
+                    .takes_value(true)
+                    .help("New entry will be added before the given key"))
+                .arg(Arg::with_name("after")
+                    .long("after")
+                    .takes_value(true)
+                    .help("New entry will be added after the given key"))
+                .arg(Arg::with_name("first")
+                    .long("first")
+                    .help("New entry will be added at the start of section"))
+                .arg(Arg::with_name("sectionbefore")
+                    .long("section-before")
+                    .takes_value(true)
+                    .help("New section will be added before the given section"))
+                .arg(Arg::with_name("sectionafter")
+                    .long("section-after")
+                    .takes_value(true)
+                    .help("New section will be added after the given section"))
+                .arg(Arg::with_name("sectionsorted")
+                    .long("section-sorted")
+                    .help("New section will be added in alphabetical order"))
                 .arg(Arg::with_name("reformat")
                     .long("pretty-print")
                     .help("Format output to look nicer"))
@@ -61,6 +126,54 @@ fn main() {
                 .arg(Arg::with_name("nocomments")
                     .long("no-comments")
                     .help("Remove all comments"))
+                .arg(Arg::with_name("sortsections")
+                    .long("sort-sections")
+                    .help("Sort sections by name"))
+                .arg(Arg::with_name("sortkeys")
+                    .long("sort-keys")
+                    .help("Sort keys by name within each section"))
+                .arg(Arg::with_name("dedupe")
+                    .long("dedupe")
+                    .takes_value(true)
+                    .possible_values(&["first", "last", "error"])
+                    .help("Merge repeated sections and remove duplicate keys"))
+                .arg(Arg::with_name("eol")
+                    .long("eol")
+                    .takes_value(true)
+                    .possible_values(&["lf", "crlf", "keep", "auto"])
+                    .help("Line ending to use"))
+                .arg(Arg::with_name("dialect")
+                    .long("dialect")
+                    .takes_value(true)
+                    .possible_values(&["ini", "dotenv", "systemd", "desktop", "reg", "mysql", "php", "windows", "gitconfig"])
+                    .help("File format to use instead of one detected from file name"))
+                .arg(Arg::with_name("commentchars")
+                    .long("comment-chars")
+                    .takes_value(true)
+                    .help("Space separated characters starting a comment"))
+                .arg(Arg::with_name("encoding")
+                    .long("encoding")
+                    .takes_value(true)
+                    .possible_values(&["utf-8", "utf-8-bom", "utf-16le", "utf-16be"])
+                    .help("Encoding to use instead of the detected one"))
+                .arg(Arg::with_name("convertencoding")
+                    .long("convert-encoding")
+                    .takes_value(true)
+                    .possible_values(&["utf-8", "utf-8-bom", "utf-16le", "utf-16be"])
+                    .help("Encoding to convert content to"))
+                .arg(Arg::with_name("strict")
+                    .long("strict")
+                    .help("Fails on lines that cannot be parsed"))
+                .arg(Arg::with_name("effective")
+                    .long("effective")
+                    .help("Resolves drop-in (systemd) and included (mysql) files and duplicate keys"))
+                .arg(Arg::with_name("overlay")
+                    .long("overlay")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .value_name("file")
+                    .help("File whose entries override and extend the processed one; can be repeated"))
                 .arg(Arg::with_name("inplace")
                     .short("i")
                     .long("in-place")
@@ -85,27 +198,96 @@ fn main() {
     let exec_reformat = args.is_present("reformat");
     let exec_nocomments = args.is_present("nocomments");
     let exec_trim = args.is_present("trim");
+    let exec_sortsections = args.is_present("sortsections");
+    let exec_sortkeys = args.is_present("sortkeys");
+    let exec_effective = args.is_present("effective");
+    let exec_strict = args.is_present("strict");
 
-    let find_section = args.value_of("section");
-    let find_key = args.value_of("key");
+    let file_name = args.value_of("filename");
+
+    let mut dialect = match args.value_of("dialect") {
+        Some(dialect_name) => IniDialect::from_name(dialect_name).unwrap(),
+        None               => file_name.and_then(IniDialect::from_file_name).unwrap_or_else(IniDialect::ini),
+    };
+    if args.is_present("ignorecase") { dialect.set_ignore_case(); }
+    if let Some(comment_chars) = args.value_of("commentchars") {
+        dialect.set_comment_chars(comment_chars.split_whitespace().map(|comment_chars| comment_chars.to_string()).collect());
+    }
+    let dialect = dialect;
+
+    let encoding = args.value_of("encoding").map(|encoding_name| IniEncoding::from_name(encoding_name).unwrap());
+    let convert_encoding = args.value_of("convertencoding").map(|encoding_name| IniEncoding::from_name(encoding_name).unwrap());
+
+    if !dialect.has_sections() && args.is_present("section") {
+        eprintln!("error: sections are not supported by this file format");
+        std::process::exit(255);
+    }
+
+    let section_arg = value_of_lossless(&args, "section");
+    let key_arg = value_of_lossless(&args, "key");
+    let locale_arg = value_of_lossless(&args, "locale");
+
+    let find_section = if dialect.has_sections() { section_arg.as_deref() } else { Some("") }; //empty section covers entries without section
+    let find_key = key_arg.as_deref();
+    let find_locale = locale_arg.as_deref();
+    let localized_key = match (find_key, find_locale) {
+        (Some(key), Some(locale)) => Some(format!("{}[{}]", key, locale)),
+        (key, _)                  => key.map(|key| key.to_string()),
+    };
 
     let should_print    = args.is_present("print");
     let should_printraw = args.is_present("printraw");
+    let should_exists   = args.is_present("exists");
+    let should_lint     = args.is_present("lint");
     let should_delete   = args.is_present("delete");
-    let should_append   = args.value_of("append");
-    let should_change   = args.value_of("change");
-    let should_edit     = args.value_of("edit");
+    let should_addflag  = args.is_present("addflag");
+    let append_arg     = value_of_lossless(&args, "append");
+    let change_arg     = value_of_lossless(&args, "change");
+    let edit_arg       = value_of_lossless(&args, "edit");
+    let additem_arg    = value_of_lossless(&args, "additem");
+    let removeitem_arg = value_of_lossless(&args, "removeitem");
+    let comment_arg    = value_of_lossless(&args, "comment");
+    let copyto_arg     = value_of_lossless(&args, "copyto");
+    let moveto_arg     = value_of_lossless(&args, "moveto");
+    let before_arg     = value_of_lossless(&args, "before");
+    let after_arg      = value_of_lossless(&args, "after");
+    let sectionbefore_arg = value_of_lossless(&args, "sectionbefore");
+    let sectionafter_arg  = value_of_lossless(&args, "sectionafter");
+    let should_append   = append_arg.as_deref();
+    let should_change   = change_arg.as_deref();
+    let should_edit     = edit_arg.as_deref();
+    let should_additem    = additem_arg.as_deref();
+    let should_removeitem = removeitem_arg.as_deref();
+    let should_copyto     = copyto_arg.as_deref();
+    let should_moveto     = moveto_arg.as_deref();
 
     let mut operation_count = 0;
     if should_print            { operation_count += 1; }
     if should_printraw         { operation_count += 1; }
+    if should_exists           { operation_count += 1; }
+    if should_lint             { operation_count += 1; }
     if should_delete           { operation_count += 1; }
     if should_append.is_some() { operation_count += 1; }
     if should_change.is_some() { operation_count += 1; }
     if should_edit.is_some()   { operation_count += 1; }
+    if should_addflag          { operation_count += 1; }
+    if should_additem.is_some()    { operation_count += 1; }
+    if should_removeitem.is_some() { operation_count += 1; }
+    if should_copyto.is_some()     { operation_count += 1; }
+    if should_moveto.is_some()     { operation_count += 1; }
     let operation_count = operation_count;
     if operation_count > 1 {
-        eprintln!("error: only one operation (delete, append, change, edit, or print) is allowed");
+        eprintln!("error: only one operation (delete, append, change, edit, add-flag, add-item, remove-item, copy-to, move-to, print, exists, or lint) is allowed");
+        std::process::exit(255);
+    }
+
+    if exec_effective && !dialect.has_drop_ins() && !dialect.has_includes() && dialect.get_duplicates() == IniDuplicates::Allowed {
+        eprintln!("error: effective value is not supported by this file format");
+        std::process::exit(255);
+    }
+
+    if exec_effective && (exec_inplace || (operation_count > 0 && !should_print && !should_printraw && !should_exists)) {
+        eprintln!("error: effective value can only be shown");
         std::process::exit(255);
     }
 
@@ -114,6 +296,56 @@ fn main() {
         std::process::exit(255);
     }
 
+    if should_exists && exec_inplace {
+        eprintln!("error: cannot both check existence and replace in-place");
+        std::process::exit(255);
+    }
+
+    if should_exists && !args.is_present("section") && find_key.is_none() {
+        eprintln!("error: section or key must be specified for exists operation");
+        std::process::exit(255);
+    }
+
+    if should_lint && exec_inplace {
+        eprintln!("error: cannot both lint and replace in-place");
+        std::process::exit(255);
+    }
+
+    if should_delete && !dialect.has_sections() && find_key.is_none() {
+        eprintln!("error: key must be specified for delete operation");
+        std::process::exit(255);
+    }
+
+    if find_locale.is_some() && find_key.is_none() {
+        eprintln!("error: key must be specified for locale");
+        std::process::exit(255);
+    }
+
+    if should_addflag && !dialect.has_flags() {
+        eprintln!("error: keys without value are not supported by this file format");
+        std::process::exit(255);
+    }
+
+    if should_addflag && (find_section.is_none() || find_key.is_none()) {
+        eprintln!("error: both section and key must be specified for add-flag operation");
+        std::process::exit(255);
+    }
+
+    if (should_append.is_some() || should_edit.is_some() || should_addflag || should_additem.is_some()) && find_key.is_some_and(|key| !dialect.is_valid_key(key)) {
+        eprintln!("error: key cannot contain separator");
+        std::process::exit(255);
+    }
+
+    if (should_additem.is_some() || should_removeitem.is_some()) && !dialect.has_lists() {
+        eprintln!("error: list values are not supported by this file format");
+        std::process::exit(255);
+    }
+
+    if (should_additem.is_some() || should_removeitem.is_some()) && (find_section.is_none() || find_key.is_none()) {
+        eprintln!("error: both section and key must be specified for list operation");
+        std::process::exit(255);
+    }
+
     if should_append.is_some()  && (find_section.is_none() || find_key.is_none()) {
         eprintln!("error: both section and key must be specified for append operation");
         std::process::exit(255);
@@ -129,59 +361,240 @@ fn main() {
         std::process::exit(255);
     }
 
+    if (should_copyto.is_some() || should_moveto.is_some()) && !dialect.has_sections() {
+        eprintln!("error: sections are not supported by this file format");
+        std::process::exit(255);
+    }
 
-    let file_name = args.value_of("filename");
+    if (should_copyto.is_some() || should_moveto.is_some()) && (find_section.is_none() || find_key.is_none()) {
+        eprintln!("error: both section and key must be specified for copy or move operation");
+        std::process::exit(255);
+    }
 
-    let file = IniFile::parse(file_name);
+    if args.is_present("withdocs") && operation_count > 0 && !should_print && !should_printraw {
+        eprintln!("error: comments can only be shown together with values");
+        std::process::exit(255);
+    }
+
+    if args.is_present("withdocs") && find_locale.is_some() {
+        eprintln!("error: comments cannot be shown for localized key");
+        std::process::exit(255);
+    }
+
+    if comment_arg.is_some() && should_append.is_none() && should_change.is_none() && should_edit.is_none() {
+        eprintln!("error: comment can only be added with append, change, or edit operation");
+        std::process::exit(255);
+    }
+
+    let position = match (before_arg, after_arg, args.is_present("first")) {
+        (None,             None,            false) => IniPosition::Last,
+        (Some(before_key), None,            false) => IniPosition::Before(before_key),
+        (None,             Some(after_key), false) => IniPosition::After(after_key),
+        (None,             None,            true)  => IniPosition::First,
+        _ => {
+            eprintln!("error: only one position (before, after, or first) is allowed");
+            std::process::exit(255);
+        },
+    };
+
+    if !matches!(position, IniPosition::Last) && should_append.is_none() && should_edit.is_none() {
+        eprintln!("error: position can only be given for append or edit operation");
+        std::process::exit(255);
+    }
+
+    let section_position = match (sectionbefore_arg, sectionafter_arg, args.is_present("sectionsorted")) {
+        (None,                 None,                false) => IniSectionPosition::Last,
+        (Some(before_section), None,                false) => IniSectionPosition::Before(before_section),
+        (None,                 Some(after_section), false) => IniSectionPosition::After(after_section),
+        (None,                 None,                true)  => IniSectionPosition::Sorted,
+        _ => {
+            eprintln!("error: only one section position (section-before, section-after, or section-sorted) is allowed");
+            std::process::exit(255);
+        },
+    };
+
+    if exec_sortsections && !dialect.has_sections() {
+        eprintln!("error: sections are not supported by this file format");
+        std::process::exit(255);
+    }
+
+    if !matches!(section_position, IniSectionPosition::Last) && !dialect.has_sections() {
+        eprintln!("error: sections are not supported by this file format");
+        std::process::exit(255);
+    }
+
+    if !matches!(section_position, IniSectionPosition::Last) && should_append.is_none() && should_edit.is_none() && !should_addflag && should_additem.is_none() && should_copyto.is_none() && should_moveto.is_none() {
+        eprintln!("error: section position can only be given for operations adding entries");
+        std::process::exit(255);
+    }
+
+    if comment_arg.as_deref().unwrap_or("").contains(['\r', '\n']) {
+        eprintln!("error: comment must be on a single line");
+        std::process::exit(255);
+    }
+
+
+    if exec_effective && file_name.is_none() {
+        eprintln!("error: file must be specified for effective value");
+        std::process::exit(255);
+    }
+
+    let file = if exec_effective {
+        IniFile::parse_effective(file_name.unwrap(), &dialect, encoding, exec_strict)
+    } else {
+        IniFile::parse(file_name, &dialect, encoding, exec_strict)
+    };
     match file {
         Ok(mut file) => {
+            if !file.get_invalid_lines().is_empty() {
+                let line_numbers: Vec<String> = file.get_invalid_lines().iter().map(|line_number| line_number.to_string()).collect();
+                eprintln!("warning: invalid UTF-8 bytes will be preserved as-is (line {})", line_numbers.join(", "));
+            }
+            if let Some(convert_encoding) = convert_encoding { file.set_encoding(convert_encoding); }
+            if let Some(overlay_names) = args.values_of("overlay") {
+                for overlay_name in overlay_names { //later files override earlier ones
+                    let overlay = if exec_effective {
+                        IniFile::parse_effective(overlay_name, &dialect, encoding, exec_strict)
+                    } else {
+                        IniFile::parse(Some(overlay_name), &dialect, encoding, exec_strict)
+                    };
+                    match overlay {
+                        Ok(overlay) => { file.overlay(&overlay); },
+                        Err(err)    => { exit_with_read_error(err); },
+                    }
+                }
+            }
+            file.set_edit_options(IniEditOptions {
+                raw:      args.is_present("raw"),
+                comment:  comment_arg,
+                position,
+                section_position,
+            });
             if exec_nocomments { file.remove_comments(); }
             if exec_trim { file.trim(); }
 
+            if let Some(dedupe_policy) = args.value_of("dedupe") {
+                let policy = match dedupe_policy {
+                    "first" => IniDedupe::KeepFirst,
+                    "last"  => IniDedupe::KeepLast,
+                    _       => IniDedupe::Error,
+                };
+                let issues = file.dedupe(policy);
+                for issue in &issues { //output might be file content so report goes to stderr
+                    match file_name {
+                        Some(file_name) => { eprintln!("{}:{}", file_name, issue); },
+                        None            => { eprintln!("{}", issue); },
+                    }
+                }
+                if policy == IniDedupe::Error && !issues.is_empty() { std::process::exit(7); }
+            }
+
             if operation_count == 0 {
-                if find_section.is_some() || find_key.is_some() {
+                if let (Some(find_key), Some(find_locale)) = (find_key, find_locale) {
+                    file.filter_localized(find_section, find_key, find_locale);
+                } else if (args.is_present("section") || find_key.is_some()) && args.is_present("withdocs") {
+                    file.filter_with_docs(find_section, find_key);
+                } else if args.is_present("section") || find_key.is_some() {
                     file.filter(find_section, find_key); //just filter stuff out
                 }
             } else if should_print || should_printraw { //just show value
-                file.filter(find_section, find_key);
+                file.resolve_resets();
+                if let (Some(find_key), Some(find_locale)) = (find_key, find_locale) {
+                    file.filter_localized(find_section, find_key, find_locale);
+                } else if args.is_present("withdocs") {
+                    file.filter_with_docs(find_section, find_key);
+                } else {
+                    file.filter(find_section, find_key);
+                }
+                let mut values = Vec::new(); //each value with comments above it
+                let mut docs = Vec::new();
                 for line in file {
                     let content = line.get_content();
                     match content {
                         IniContent::Entry(entry)   => {
                             if should_printraw {
-                                println!("{}", entry.get_value());
+                                values.push((docs, entry.get_value().to_string()));
                             } else {
-                                println!("{}", entry.get_value_unquoted());
+                                values.push((docs, entry.get_value_unquoted()));
                             }
+                            docs = Vec::new();
                         },
+                        IniContent::Comment(comment) => { docs.push(comment.to_string()); },
+                        IniContent::Section(_)       => { docs.clear(); }, //section documentation is not shown with values
                         _ => { },
                     }
                 }
+                if exec_effective && find_key.is_some() && values.len() > 1 {
+                    match dialect.get_duplicates() {
+                        IniDuplicates::FirstWins => { values.truncate(1); },
+                        IniDuplicates::LastWins  => { values.drain(..values.len() - 1); },
+                        IniDuplicates::Allowed   => { },
+                    }
+                }
+                for (docs, value) in values {
+                    for doc in docs {
+                        println_lossless(&doc);
+                    }
+                    println_lossless(&value);
+                }
                 std::process::exit(0); //no need for standard printout
+            } else if should_exists { //just check presence
+                std::process::exit(if file.contains(find_section, localized_key.as_deref()) { 0 } else { 6 });
+            } else if should_lint { //just show problems
+                let issues = file.lint();
+                for issue in &issues {
+                    match file_name {
+                        Some(file_name) => { println_lossless(&format!("{}:{}", file_name, issue)); },
+                        None            => { println_lossless(&issue.to_string()); },
+                    }
+                }
+                std::process::exit(if issues.is_empty() { 0 } else { 5 });
             } else if should_delete {
-                file.delete(find_section, find_key);
+                file.delete(find_section, localized_key.as_deref());
             } else if should_append.is_some() {
-                file.edit(find_section.unwrap(), find_key.unwrap(), should_append.unwrap(), false, true);
+                file.edit(find_section.unwrap(), &localized_key.unwrap(), should_append.unwrap(), false, true);
             } else if should_change.is_some() {
-                file.edit(find_section.unwrap(), find_key.unwrap(), should_change.unwrap(), true, false);
+                file.edit(find_section.unwrap(), &localized_key.unwrap(), should_change.unwrap(), true, false);
             } else if should_edit.is_some() {
-                file.edit(find_section.unwrap(), find_key.unwrap(), should_edit.unwrap(), true, true);
+                file.edit(find_section.unwrap(), &localized_key.unwrap(), should_edit.unwrap(), true, true);
+            } else if should_addflag {
+                file.add_flag(find_section.unwrap(), &localized_key.unwrap());
+            } else if let Some(item) = should_additem {
+                file.edit_list(find_section.unwrap(), &localized_key.unwrap(), item, true);
+            } else if let Some(item) = should_removeitem {
+                file.edit_list(find_section.unwrap(), &localized_key.unwrap(), item, false);
+            } else if let Some(new_section) = should_copyto {
+                file.copy_entry(find_section.unwrap(), &localized_key.unwrap(), new_section);
+            } else if let Some(new_section) = should_moveto {
+                file.move_entry(find_section.unwrap(), &localized_key.unwrap(), new_section);
             }
 
+            if exec_sortsections { file.sort_sections(); }
+            if exec_sortkeys { file.sort_keys(); }
             if exec_reformat { file.reformat(); }
 
+            match args.value_of("eol") {
+                Some("lf")   => { file.set_line_endings("\n"); },
+                Some("crlf") => { file.set_line_endings("\r\n"); },
+                Some("auto") => {
+                    let line_ending = file.get_common_line_ending().to_string();
+                    file.set_line_endings(&line_ending);
+                },
+                _            => { },
+            }
+
             if exec_inplace {
                 match file.save(file_name) {
                     Ok(_) => { },
-                    Err(err) => {
-                        match file_name {
-                            Some(file_name) => { eprintln!("error: cannot write file '{}': {}", file_name, err); },
-                            None            => { eprintln!("error: cannot write to output: {}", err); },
-                        }
-                        std::process::exit(2);
-                    },
+                    Err(err) => { exit_with_write_error(err); },
+                }
+            } else if !show_info && !show_debug { //final output in the same encoding
+                match file.save(None) {
+                    Ok(_) => { },
+                    Err(err) => { exit_with_write_error(err); },
                 }
-            } else { //final output
+                std::process::exit(0);
+            } else { //final output with line details
                 let mut line_number = 0;
                 let line_number_digit_count = (file.line_count() as f64).log10().ceil() as usize;
                 for line in file {
@@ -202,21 +615,63 @@ fn main() {
                             IniContent::Section(_) => { print!(" S"); },
                             IniContent::Entry(_)   => { print!(" E"); },
                             IniContent::Comment(_) => { print!(" C"); },
+                            IniContent::Directive(_) => { print!(" D"); },
                             IniContent::Other(_)   => { print!(" O"); },
                         }
                     }
                     if show_info || show_debug { print!(": "); }
-                    println!("{}", line.get_content());
+                    println_lossless(&line.get_content().to_string());
                 }
                 std::process::exit(0);
             }
         },
-        Err(err) => {
-            match file_name {
+        Err(err) => { exit_with_read_error(err); },
+    }
+}
+
+fn exit_with_read_error(err: IniError) -> ! {
+    match err {
+        IniError::Io { .. } => {
+            match err.get_file_name() {
                 Some(file_name) => { eprintln!("error: cannot read file '{}': {}", file_name, err); },
                 None            => { eprintln!("error: cannot read from input: {}", err); },
             }
             std::process::exit(1);
         },
+        IniError::Encoding { .. } => {
+            eprintln!("error: {}", err);
+            std::process::exit(3);
+        },
+        IniError::Syntax { .. } => {
+            eprintln!("error: {}", err);
+            std::process::exit(4);
+        },
     }
 }
+
+fn exit_with_write_error(err: IniError) -> ! {
+    match err {
+        IniError::Io { .. } => {
+            match err.get_file_name() {
+                Some(file_name) => { eprintln!("error: cannot write file '{}': {}", file_name, err); },
+                None            => { eprintln!("error: cannot write to output: {}", err); },
+            }
+            std::process::exit(2);
+        },
+        _ => {
+            eprintln!("error: {}", err);
+            std::process::exit(3);
+        },
+    }
+}
+
+
+fn value_of_lossless(args: &ArgMatches, name: &str) -> Option<String> { //invalid UTF-8 is kept the same way as in file
+    args.value_of_os(name).map(|value| ini::from_bytes_lossless(value.as_encoded_bytes()))
+}
+
+fn println_lossless(text: &str) {
+    let mut bytes = ini::to_bytes_lossless(text);
+    bytes.push(b'\n');
+    let _ = std::io::stdout().write_all(&bytes);
+}